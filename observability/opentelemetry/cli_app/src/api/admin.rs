@@ -0,0 +1,18 @@
+use super::handlers;
+use crate::api::middleware::auth::{admin, auth, AuthPolicy};
+use crate::state::ApplicationState;
+use axum::routing::get;
+use axum::{middleware, Extension, Router};
+use std::sync::Arc;
+
+pub fn configure(state: Arc<ApplicationState>) -> Router {
+    Router::new().route(
+        "/log-level",
+        get(handlers::admin::get_log_level)
+            .put(handlers::admin::update_log_level)
+            .with_state(state.clone())
+            .route_layer(middleware::from_fn(admin))
+            .route_layer(middleware::from_fn_with_state(state, auth))
+            .route_layer(Extension(AuthPolicy::BearerTokenOrClientCertificate)),
+    )
+}
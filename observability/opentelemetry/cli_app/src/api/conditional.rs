@@ -0,0 +1,139 @@
+use crate::api::errors::AppError;
+use axum::http::header::{self, HeaderMap, HeaderValue};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Serializes `body` as JSON with a strong `ETag` over its bytes and, if
+/// given, `Last-Modified`. Answers with 304 when the request's
+/// `If-None-Match` or `If-Modified-Since` show the client is up to date.
+pub fn respond<T: Serialize>(
+    request_headers: &HeaderMap,
+    body: &T,
+    last_modified: Option<DateTime<Utc>>,
+    cache_control: &str,
+) -> Result<Response, AppError> {
+    let bytes = serde_json::to_vec(body).map_err(anyhow::Error::from)?;
+    let etag = etag(&bytes);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, header_value(&etag)?);
+    headers.insert(header::CACHE_CONTROL, header_value(cache_control)?);
+    if let Some(last_modified) = last_modified {
+        headers.insert(
+            header::LAST_MODIFIED,
+            header_value(&last_modified.format(HTTP_DATE_FORMAT).to_string())?,
+        );
+    }
+
+    if is_not_modified(request_headers, &etag, last_modified) {
+        return Ok((StatusCode::NOT_MODIFIED, headers).into_response());
+    }
+
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    Ok((headers, bytes).into_response())
+}
+
+/// Serializes `body` as JSON with its strong `ETag`, e.g. for responses to
+/// writes or for the current representation after a failed precondition.
+pub fn with_etag<T: Serialize>(status: StatusCode, body: &T) -> Result<Response, AppError> {
+    let bytes = serde_json::to_vec(body).map_err(anyhow::Error::from)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, header_value(&etag(&bytes))?);
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    Ok((status, headers, bytes).into_response())
+}
+
+/// The `ETag` that `respond` and `with_etag` send for `body`.
+pub fn etag_of<T: Serialize>(body: &T) -> Result<String, AppError> {
+    let bytes = serde_json::to_vec(body).map_err(anyhow::Error::from)?;
+
+    Ok(etag(&bytes))
+}
+
+/// The tag of the uncompressed JSON; `middleware::etag` makes it unique
+/// per content encoding.
+fn etag(bytes: &[u8]) -> String {
+    format!("\"{:x}\"", Sha256::digest(bytes))
+}
+
+/// The entity tag without the weakness indicator.
+fn opaque_tag(etag: &str) -> &str {
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+/// `If-None-Match` takes precedence; `If-Modified-Since` is only evaluated
+/// when the request has no `If-None-Match`, as required by RFC 9110.
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+    if let Some(list) = headers.get(header::IF_NONE_MATCH) {
+        return list
+            .to_str()
+            .map(|value| if_none_match(value, etag))
+            .unwrap_or(false);
+    }
+
+    let if_modified_since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+
+    match (if_modified_since, last_modified) {
+        (Some(since), Some(last_modified)) => last_modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
+}
+
+/// Weak comparison of a list of entity tags against `etag`, as used for
+/// `If-None-Match`.
+fn if_none_match(list: &str, etag: &str) -> bool {
+    list.split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || opaque_tag(candidate) == opaque_tag(etag))
+}
+
+/// Strong comparison of an `If-Match` list against `etag`, as required by
+/// RFC 9110; weak entity tags never match.
+pub fn if_match(list: &str, etag: &str) -> bool {
+    list.split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || (!candidate.starts_with("W/") && candidate == etag))
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, AppError> {
+    HeaderValue::from_str(value).map_err(|e| AppError::from(anyhow::Error::from(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{if_match, if_none_match};
+
+    #[test]
+    fn if_match_compares_strongly() {
+        assert!(if_match("\"a\"", "\"a\""));
+        assert!(if_match("\"b\", \"a\"", "\"a\""));
+        assert!(if_match("*", "\"a\""));
+        assert!(!if_match("W/\"a\"", "\"a\""));
+        assert!(!if_match("\"b\"", "\"a\""));
+    }
+
+    #[test]
+    fn if_none_match_compares_weakly() {
+        assert!(if_none_match("W/\"a\"", "\"a\""));
+        assert!(if_none_match("\"b\", \"a\"", "\"a\""));
+        assert!(if_none_match("*", "\"a\""));
+        assert!(!if_none_match("\"b\"", "\"a\""));
+    }
+}
@@ -1,5 +1,8 @@
+use crate::api::middleware::request_id::RequestId;
+use crate::api::response::error::ErrorResponse;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
+use axum::Json;
 
 pub struct AppError(StatusCode, anyhow::Error);
 
@@ -18,6 +21,22 @@ impl From<anyhow::Error> for AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        (self.0, format!("{:?}", self.1)).into_response()
+        let request_id = RequestId::current().map(|id| id.0);
+
+        // Clients only get the outermost message; the causes may describe
+        // internals, e.g. queries, and are only logged.
+        if self.0.is_server_error() {
+            tracing::error!(request_id, status = self.0.as_u16(), "{:?}", self.1);
+        } else {
+            tracing::info!(request_id, status = self.0.as_u16(), "{:?}", self.1);
+        }
+
+        let response = ErrorResponse {
+            status: "error".to_string(),
+            message: self.1.to_string(),
+            request_id,
+        };
+
+        (self.0, Json(response)).into_response()
     }
 }
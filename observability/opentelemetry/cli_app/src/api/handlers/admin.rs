@@ -0,0 +1,44 @@
+use crate::api::errors::AppError;
+use crate::api::request::admin::UpdateLogLevelRequest;
+use crate::api::response::admin::LogLevelResponse;
+use crate::api::response::TokenClaims;
+use crate::state::ApplicationState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn get_log_level(
+    State(state): State<Arc<ApplicationState>>,
+) -> Result<Json<LogLevelResponse>, AppError> {
+    let response = LogLevelResponse {
+        data: state.log_level.get(),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn update_log_level(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Json(payload): Json<UpdateLogLevelRequest>,
+) -> Result<Json<LogLevelResponse>, AppError> {
+    let ttl = payload.ttl_seconds.map(Duration::from_secs);
+
+    let level = state
+        .log_level
+        .set(&payload.level, ttl)
+        .map_err(|e| AppError::from((StatusCode::BAD_REQUEST, e)))?;
+
+    tracing::warn!(
+        user = claims.sub,
+        level = level.level,
+        ttl_seconds = payload.ttl_seconds,
+        "Log level changed"
+    );
+
+    let response = LogLevelResponse { data: level };
+
+    Ok(Json(response))
+}
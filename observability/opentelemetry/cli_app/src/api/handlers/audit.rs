@@ -0,0 +1,28 @@
+use crate::api::errors::AppError;
+use crate::api::response::audit::ListAuditEventsResponse;
+use crate::services::audit::{AuditFilter, AuditService};
+use crate::state::ApplicationState;
+use axum::extract::{Query, State};
+use axum::Json;
+use std::sync::Arc;
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditFilter),
+    responses(
+        (status = 200, description = "Audit events", body = ListAuditEventsResponse),
+        (status = 403, description = "Administrator role required"),
+    ),
+)]
+pub async fn list(
+    State(state): State<Arc<ApplicationState>>,
+    Query(filter): Query<AuditFilter>,
+) -> Result<Json<ListAuditEventsResponse>, AppError> {
+    let events = state.audit_service.list_events(&filter).await?;
+
+    let response = ListAuditEventsResponse { data: events };
+
+    Ok(Json(response))
+}
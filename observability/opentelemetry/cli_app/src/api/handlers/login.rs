@@ -3,6 +3,7 @@ use crate::api::request::login::LoginRequest;
 use crate::api::response::login::LoginResponse;
 use crate::api::response::TokenClaims;
 use crate::model::validate_password;
+use crate::services::audit::{AuditContext, AuditService, NewAuditEvent};
 use crate::services::user::UserService;
 use crate::state::ApplicationState;
 use axum::extract::State;
@@ -23,24 +24,47 @@ use std::sync::Arc;
 )]
 pub async fn login(
     State(state): State<Arc<ApplicationState>>,
+    audit: AuditContext,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, AppError> {
     let user = match state.user_service.get_user_by_name(&payload.username).await {
-        Ok(user) => user,
-        Err(_) => {
+        Ok(user) => Some(user),
+        Err(_) => None,
+    };
+
+    let user = match user {
+        Some(user) if validate_password(&payload.password, &user.password).is_ok() => user,
+        user => {
+            // The username is whatever the client sent, so it is recorded as
+            // what the attempt was about, not as who made it.
+            let event = NewAuditEvent {
+                action: "user.login_failed",
+                target_type: "user",
+                target_id: user.map(|user| user.id.to_string()),
+                changes: serde_json::json!({ "username": payload.username }),
+            };
+            if let Err(e) = state.audit_service.record(&audit, event).await {
+                tracing::error!("Failed to record audit event: {:?}", e);
+            }
+
             return Err(AppError::from((
                 StatusCode::UNAUTHORIZED,
                 anyhow::anyhow!("Invalid username or password"),
-            )))
+            )));
         }
     };
 
-    if validate_password(&payload.password, &user.password).is_err() {
-        return Err(AppError::from((
-            StatusCode::UNAUTHORIZED,
-            anyhow::anyhow!("Invalid username or password"),
-        )));
-    }
+    let event = NewAuditEvent {
+        action: "user.login",
+        target_type: "user",
+        target_id: Some(user.id.to_string()),
+        changes: serde_json::Value::Null,
+    };
+    let audit = AuditContext {
+        actor: Some(user.username.clone()),
+        ..audit
+    };
+    state.audit_service.record(&audit, event).await?;
 
     let secret = state
         .settings
@@ -56,6 +80,7 @@ pub async fn login(
         (now + chrono::Duration::try_seconds(timeout).unwrap_or_default()).timestamp() as usize;
     let claims = TokenClaims {
         sub: payload.username,
+        role: user.role,
         exp,
         iat,
     };
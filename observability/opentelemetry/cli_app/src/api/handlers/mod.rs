@@ -1,3 +1,7 @@
+pub mod admin;
+pub mod audit;
 pub mod hello;
 pub mod login;
 pub mod posts;
+pub mod revisions;
+pub mod users;
@@ -1,15 +1,46 @@
+use crate::api::conditional;
 use crate::api::errors::AppError;
+use crate::api::patch::{self, PatchFormat};
+use crate::api::request::auth::OptionalClaims;
+use crate::api::request::posts::{ListPostsQuery, ScheduleQuery};
 use crate::api::response::posts::ListPostsResponse;
 use crate::api::response::posts::SinglePostResponse;
 use crate::api::response::TokenClaims;
+use crate::model::{validate_password, Post, PostStatus, PostVisibility, UserRole};
+use crate::services::audit::AuditContext;
 use crate::services::post::PostService;
-use crate::services::post::{CreatePostRequest, UpdatePostRequest};
+use crate::services::post::{
+    Access, CreatePostRequest, InvalidRequest, InvalidTransition, PatchPostRequest, Reader,
+    RejectPostRequest, SlugTaken, UpdatePostRequest, VersionConflict, WorkflowAction,
+};
+use crate::services::user::UserService;
 use crate::state::ApplicationState;
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::body::Bytes;
+use axum::extract::{MatchedPath, OriginalUri, Path, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::{Extension, Json};
+use chrono::{Duration, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use std::sync::Arc;
 
+/// Fields of a post a patch may change, all others are read-only.
+const PATCHABLE_FIELDS: [&str; 7] = [
+    "slug",
+    "title",
+    "content",
+    "status",
+    "publish_at",
+    "unpublish_at",
+    "visibility",
+];
+
+/// Fields a patch may set that are never part of a post in responses.
+const WRITE_ONLY_FIELDS: [&str; 2] = ["password", "message"];
+
+/// Header with the password of a password-protected post.
+const POST_PASSWORD: &str = "x-post-password";
+
 #[utoipa::path(
     post,
     path = "/posts",
@@ -17,14 +48,47 @@ use std::sync::Arc;
     request_body = CreatePostRequest,
     responses(
         (status = 200, description = "Post create", body = SinglePostResponse),
+        (status = 403, description = "Only editors may create posts for other users", body = ErrorResponse),
+        (status = 409, description = "Slug is already taken or status is not a valid initial status", body = ErrorResponse),
+        (status = 422, description = "Invalid slug or schedule, or unknown author", body = ErrorResponse),
     ),
 )]
 pub async fn create(
-    Extension(_claims): Extension<TokenClaims>,
+    Extension(claims): Extension<TokenClaims>,
     State(state): State<Arc<ApplicationState>>,
-    Json(payload): Json<CreatePostRequest>,
+    audit: AuditContext,
+    Json(mut payload): Json<CreatePostRequest>,
 ) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.create_post(payload).await?;
+    let user = state
+        .user_service
+        .get_user_by_name(&claims.sub)
+        .await
+        .map_err(|e| AppError::from((StatusCode::UNAUTHORIZED, e)))?;
+
+    payload.author_id = match payload.on_behalf_of {
+        Some(author_id) if author_id != user.id => {
+            if claims.role < UserRole::Editor {
+                return Err(AppError::from((
+                    StatusCode::FORBIDDEN,
+                    anyhow::anyhow!("Only editors may create posts for other users"),
+                )));
+            }
+
+            state
+                .user_service
+                .get_user_by_id(author_id)
+                .await
+                .map_err(|e| AppError::from((StatusCode::UNPROCESSABLE_ENTITY, e)))?
+                .id
+        }
+        _ => user.id,
+    };
+
+    let post = state
+        .post_service
+        .create_post(payload, &audit)
+        .await
+        .map_err(service_error)?;
 
     let response = SinglePostResponse { data: post };
 
@@ -36,69 +100,607 @@ pub async fn create(
     path = "/posts/{id}",
     params(
         ("id" = i64, Path, description = "ID of the post"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the post the update is based on"),
     ),
     tag = "posts",
     request_body = UpdatePostRequest,
     responses(
         (status = 200, description = "Post updates", body = SinglePostResponse),
+        (status = 403, description = "Not the author of the post or role does not allow the status change", body = ErrorResponse),
+        (status = 409, description = "Version is outdated, current post, slug is already taken or status change is not allowed", body = SinglePostResponse),
+        (status = 412, description = "ETag is outdated, current post", body = SinglePostResponse),
+        (status = 422, description = "Invalid slug or schedule", body = ErrorResponse),
+        (status = 428, description = "Neither If-Match nor version given", body = ErrorResponse),
     ),
 )]
 pub async fn update(
-    Extension(_claims): Extension<TokenClaims>,
+    Extension(claims): Extension<TokenClaims>,
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
-    Json(payload): Json<UpdatePostRequest>,
-) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.update_post(id, payload).await?;
+    headers: HeaderMap,
+    audit: AuditContext,
+    Json(mut payload): Json<UpdatePostRequest>,
+) -> Result<Response, AppError> {
+    let current = state
+        .post_service
+        .get_post_by_id(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+    let reader = reader(&state, Some(&claims)).await?;
+    authorize_owner(reader, &current)?;
+    let response = SinglePostResponse { data: current };
 
-    let response = SinglePostResponse { data: post };
+    // An ETag is checked against the current post and turned into the
+    // version it stands for, so the service can check it atomically.
+    let conflict_status = match headers.get(header::IF_MATCH) {
+        Some(if_match) => {
+            let if_match = if_match.to_str().unwrap_or_default();
+            if !conditional::if_match(if_match, &conditional::etag_of(&response)?) {
+                return conditional::with_etag(StatusCode::PRECONDITION_FAILED, &response);
+            }
 
-    Ok(Json(response))
+            payload.version = Some(response.data.version);
+            StatusCode::PRECONDITION_FAILED
+        }
+        None if payload.version.is_some() => StatusCode::CONFLICT,
+        None => {
+            return Err(AppError::from((
+                StatusCode::PRECONDITION_REQUIRED,
+                anyhow::anyhow!("If-Match header or version field is required"),
+            )))
+        }
+    };
+
+    // Checked against the post as it is now; the service rejects the update
+    // if it is based on another version.
+    authorize_transition(reader, &response.data, payload.status)?;
+
+    match state.post_service.update_post(id, payload, &audit).await {
+        Ok(post) => conditional::with_etag(StatusCode::OK, &SinglePostResponse { data: post }),
+        Err(e) => match e.downcast::<VersionConflict>() {
+            Ok(VersionConflict(current)) => {
+                conditional::with_etag(conflict_status, &SinglePostResponse { data: current })
+            }
+            Err(e) => Err(service_error(e)),
+        },
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/posts/{id}",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the post the patch is based on"),
+    ),
+    tag = "posts",
+    request_body(
+        content = Object,
+        content_type = "application/merge-patch+json",
+        description = "JSON Merge Patch (RFC 7386) of the post, or a JSON Patch (RFC 6902) sent as application/json-patch+json",
+    ),
+    responses(
+        (status = 200, description = "Patched post", body = SinglePostResponse),
+        (status = 403, description = "Not the author of the post or role does not allow the status change", body = ErrorResponse),
+        (status = 409, description = "Post changed concurrently, a test operation failed, slug is already taken or status change is not allowed", body = SinglePostResponse),
+        (status = 412, description = "ETag is outdated, current post", body = SinglePostResponse),
+        (status = 415, description = "Unsupported patch format", body = ErrorResponse),
+        (status = 422, description = "Invalid patch, slug or schedule, or read-only field changed", body = ErrorResponse),
+    ),
+)]
+pub async fn patch(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    audit: AuditContext,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let format = PatchFormat::from_headers(&headers)?;
+
+    let current = state
+        .post_service
+        .get_post_by_id(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+    let reader = reader(&state, Some(&claims)).await?;
+    authorize_owner(reader, &current)?;
+    let response = SinglePostResponse { data: current };
+
+    let conflict_status = match headers.get(header::IF_MATCH) {
+        Some(if_match) => {
+            let if_match = if_match.to_str().unwrap_or_default();
+            if !conditional::if_match(if_match, &conditional::etag_of(&response)?) {
+                return conditional::with_etag(StatusCode::PRECONDITION_FAILED, &response);
+            }
+            StatusCode::PRECONDITION_FAILED
+        }
+        None => StatusCode::CONFLICT,
+    };
+
+    // The patch is applied to the post as it is returned by the API, the
+    // changed fields are then written back.
+    let before = serde_json::to_value(&response.data).map_err(anyhow::Error::from)?;
+    let after = format.apply(&before, &body)?;
+
+    patch::check_read_only(&before, &after, &PATCHABLE_FIELDS, &WRITE_ONLY_FIELDS)?;
+
+    let req = PatchPostRequest {
+        slug: patch::changed(&before, &after, "slug")?,
+        title: patch::changed(&before, &after, "title")?,
+        content: patch::changed(&before, &after, "content")?,
+        status: patch::changed(&before, &after, "status")?,
+        visibility: patch::changed(&before, &after, "visibility")?,
+        password: patch::changed(&before, &after, "password")?,
+        publish_at: patch::changed(&before, &after, "publish_at")?,
+        unpublish_at: patch::changed(&before, &after, "unpublish_at")?,
+        message: patch::changed(&before, &after, "message")?,
+        version: Some(response.data.version),
+    };
+
+    if req.is_empty() {
+        return conditional::with_etag(StatusCode::OK, &response);
+    }
+
+    if let Some(status) = req.status {
+        authorize_transition(reader, &response.data, status)?;
+    }
+
+    match state.post_service.patch_post(id, req, &audit).await {
+        Ok(post) => conditional::with_etag(StatusCode::OK, &SinglePostResponse { data: post }),
+        Err(e) => match e.downcast::<VersionConflict>() {
+            Ok(VersionConflict(current)) => {
+                conditional::with_etag(conflict_status, &SinglePostResponse { data: current })
+            }
+            Err(e) => Err(service_error(e)),
+        },
+    }
+}
+
+/// Maps errors of the post service that are caused by the request.
+fn service_error(e: anyhow::Error) -> AppError {
+    if e.is::<SlugTaken>() || e.is::<InvalidTransition>() {
+        AppError::from((StatusCode::CONFLICT, e))
+    } else if e.is::<InvalidRequest>() {
+        AppError::from((StatusCode::UNPROCESSABLE_ENTITY, e))
+    } else {
+        e.into()
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/submit",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+    ),
+    responses(
+        (status = 200, description = "Post is in review", body = SinglePostResponse),
+        (status = 403, description = "Not the author of the post", body = ErrorResponse),
+        (status = 409, description = "Post is not a draft and no changes were requested", body = ErrorResponse),
+    ),
+)]
+pub async fn submit(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    audit: AuditContext,
+) -> Result<Response, AppError> {
+    transition(&state, &claims, id, WorkflowAction::Submit, None, &audit).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/approve",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+    ),
+    responses(
+        (status = 200, description = "Post is published, or scheduled if its publish_at is in the future", body = SinglePostResponse),
+        (status = 403, description = "Editor role required", body = ErrorResponse),
+        (status = 409, description = "Post is not in review", body = ErrorResponse),
+    ),
+)]
+pub async fn approve(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    audit: AuditContext,
+) -> Result<Response, AppError> {
+    transition(&state, &claims, id, WorkflowAction::Approve, None, &audit).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/reject",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+    ),
+    request_body = RejectPostRequest,
+    responses(
+        (status = 200, description = "Changes are requested from the author", body = SinglePostResponse),
+        (status = 403, description = "Editor role required", body = ErrorResponse),
+        (status = 409, description = "Post is not in review", body = ErrorResponse),
+        (status = 422, description = "Comment is empty", body = ErrorResponse),
+    ),
+)]
+pub async fn reject(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    audit: AuditContext,
+    Json(payload): Json<RejectPostRequest>,
+) -> Result<Response, AppError> {
+    if payload.comment.trim().is_empty() {
+        return Err(AppError::from((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            anyhow::anyhow!("A comment is required when requesting changes"),
+        )));
+    }
+
+    let comment = Some(payload.comment);
+    transition(&state, &claims, id, WorkflowAction::Reject, comment, &audit).await
+}
+
+async fn transition(
+    state: &ApplicationState,
+    claims: &TokenClaims,
+    id: i64,
+    action: WorkflowAction,
+    comment: Option<String>,
+    audit: &AuditContext,
+) -> Result<Response, AppError> {
+    let post = state
+        .post_service
+        .get_post_by_id(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    let reader = reader(state, Some(claims)).await?;
+    authorize_owner(reader, &post)?;
+    let to = action.target(&post);
+    if post.status == to {
+        return Err(service_error(
+            InvalidTransition {
+                from: post.status,
+                to,
+            }
+            .into(),
+        ));
+    }
+    authorize_transition(reader, &post, to)?;
+
+    let post = state
+        .post_service
+        .transition_post(id, action, comment, audit)
+        .await
+        .map_err(service_error)?;
+
+    conditional::with_etag(StatusCode::OK, &SinglePostResponse { data: post })
+}
+
+/// Authors may only change their own posts, editors may change all.
+fn authorize_owner(reader: Reader, post: &Post) -> Result<(), AppError> {
+    match reader.owns(post) {
+        true => Ok(()),
+        false => Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Not the author of post {}", post.id),
+        ))),
+    }
+}
+
+/// Checks the role the workflow requires for moving `post` to `to`, after
+/// `authorize_owner` let the reader change the post. Whether the transition
+/// is allowed at all is checked again by the service.
+fn authorize_transition(reader: Reader, post: &Post, to: PostStatus) -> Result<(), AppError> {
+    if post.status == to {
+        return Ok(());
+    }
+
+    let Some(role) = post.status.transition(to) else {
+        return Err(service_error(
+            InvalidTransition {
+                from: post.status,
+                to,
+            }
+            .into(),
+        ));
+    };
+
+    let allowed = match reader {
+        Reader::Editor => true,
+        _ => role == UserRole::Author,
+    };
+
+    if !allowed {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!(
+                "Not allowed to move post from {:?} to {:?}",
+                post.status,
+                to
+            ),
+        )));
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/schedule",
+    tag = "posts",
+    params(ScheduleQuery),
+    responses(
+        (status = 200, description = "Posts to be published or archived in the period, by date", body = ListPostsResponse),
+        (status = 304, description = "Not modified"),
+    ),
+)]
+pub async fn schedule(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Query(query): Query<ScheduleQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let reader = reader(&state, Some(&claims)).await?;
+    let from = query.from.unwrap_or_else(Utc::now);
+    let to = query.to.unwrap_or(from + Duration::days(30));
+
+    let posts = state.post_service.get_schedule(reader, from, to).await?;
+
+    let last_modified = posts.iter().map(|post| post.updated).max();
+    let cache_control = state.settings.load().http_cache.private();
+
+    let response = ListPostsResponse { data: posts };
+
+    conditional::respond(&headers, &response, last_modified, &cache_control)
 }
 
 #[utoipa::path(
     get,
     path = "/posts",
     tag = "posts",
+    params(ListPostsQuery),
     responses(
         (status = 200, description = "List of posts", body = ListPostsResponse),
+        (status = 304, description = "Not modified"),
     ),
 )]
 pub async fn list(
     State(state): State<Arc<ApplicationState>>,
-) -> Result<Json<ListPostsResponse>, AppError> {
-    let posts = state.post_service.get_all_posts().await?;
+    OptionalClaims(claims): OptionalClaims,
+    Query(query): Query<ListPostsQuery>,
+    path: MatchedPath,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let reader = reader(&state, claims.as_ref()).await?;
+    let posts = state
+        .post_service
+        .get_all_posts(reader, query.status)
+        .await?;
+
+    let last_modified = posts.iter().map(|post| post.updated).max();
+    let http_cache = &state.settings.load().http_cache;
+    let cache_control = match reader {
+        Reader::Anonymous => http_cache.public_for(path.as_str()),
+        _ => http_cache.private(),
+    };
 
     let response = ListPostsResponse { data: posts };
 
-    Ok(Json(response))
+    conditional::respond(&headers, &response, last_modified, &cache_control).map(vary_on_auth)
 }
 
 #[utoipa::path(
     get,
-    path = "/posts/{slug}",
+    path = "/posts/{id}",
     tag = "posts",
     params(
-        ("slug" = String, Path, description = "Slug of the post"),
+        ("id" = i64, Path, description = "ID of the post"),
+        ("X-Post-Password" = Option<String>, Header, description = "Password of a password-protected post"),
     ),
     responses(
         (status = 200, description = "Post", body = SinglePostResponse),
+        (status = 304, description = "Not modified"),
+        (status = 401, description = "Post is only visible to members or password-protected", body = ErrorResponse),
+        (status = 308, description = "Deprecated: a slug instead of an ID, or a numeric slug no post ID matches, redirects to /posts/by-slug/{slug}"),
     ),
 )]
 pub async fn get(
     State(state): State<Arc<ApplicationState>>,
+    OptionalClaims(claims): OptionalClaims,
+    Path(id): Path<String>,
+    OriginalUri(uri): OriginalUri,
+    path: MatchedPath,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    // Posts used to be looked up by slug on this route, which is kept as an
+    // alias for anything that is not an ID.
+    let Ok(post_id) = id.parse::<i64>() else {
+        return Ok(redirect_to_slug(&uri));
+    };
+
+    let post = match state.post_service.get_post_by_id(post_id).await {
+        Ok(post) => post,
+        // Slugs created before they had to contain a letter look like IDs.
+        Err(e) => match is_slug(&state, &id).await? {
+            true => return Ok(redirect_to_slug(&uri)),
+            false => return Err(AppError::from((StatusCode::NOT_FOUND, e))),
+        },
+    };
+
+    let reader = reader(&state, claims.as_ref()).await?;
+    respond_with_post(&state, reader, post, &path, &headers)
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/by-slug/{slug}",
+    tag = "posts",
+    params(
+        ("slug" = String, Path, description = "Slug of the post"),
+        ("X-Post-Password" = Option<String>, Header, description = "Password of a password-protected post"),
+    ),
+    responses(
+        (status = 200, description = "Post", body = SinglePostResponse),
+        (status = 301, description = "Old slug, redirects to the current one"),
+        (status = 304, description = "Not modified"),
+        (status = 401, description = "Post is only visible to members or password-protected", body = ErrorResponse),
+    ),
+)]
+pub async fn get_by_slug(
+    State(state): State<Arc<ApplicationState>>,
+    OptionalClaims(claims): OptionalClaims,
     Path(slug): Path<String>,
-) -> Result<Json<SinglePostResponse>, AppError> {
-    let post = state.post_service.get_post_by_slug(&slug).await;
+    OriginalUri(uri): OriginalUri,
+    path: MatchedPath,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let post = match state.post_service.get_post_by_slug(&slug).await {
+        Ok(post) => post,
+        Err(e) => match state.post_service.find_moved_slug(&slug).await? {
+            Some(current) => return Ok(redirect_to_current_slug(&uri, &current)),
+            None => return Err(AppError::from((StatusCode::NOT_FOUND, e))),
+        },
+    };
+
+    let reader = reader(&state, claims.as_ref()).await?;
+    respond_with_post(&state, reader, post, &path, &headers)
+}
+
+/// Decides which drafts the request may see. Authors are identified by the
+/// user named in the token.
+pub(crate) async fn reader(
+    state: &ApplicationState,
+    claims: Option<&TokenClaims>,
+) -> Result<Reader, AppError> {
+    let Some(claims) = claims else {
+        return Ok(Reader::Anonymous);
+    };
+
+    if claims.role >= UserRole::Editor {
+        return Ok(Reader::Editor);
+    }
+
+    let user = state
+        .user_service
+        .get_user_by_name(&claims.sub)
+        .await
+        .map_err(|e| AppError::from((StatusCode::UNAUTHORIZED, e)))?;
+
+    Ok(Reader::Author(user.id))
+}
+
+fn respond_with_post(
+    state: &ApplicationState,
+    reader: Reader,
+    post: Post,
+    path: &MatchedPath,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    match reader.access(&post) {
+        Access::Granted => {}
+        // Drafts are reported as missing, so their slugs are not revealed.
+        Access::Denied => {
+            return Err(AppError::from((
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("Post not found: {}", post.id),
+            )))
+        }
+        Access::LoginRequired => {
+            return Err(AppError::from((
+                StatusCode::UNAUTHORIZED,
+                anyhow::anyhow!("Post is only visible to members"),
+            )))
+        }
+        Access::PasswordRequired => {
+            let password = headers
+                .get(POST_PASSWORD)
+                .and_then(|password| password.to_str().ok());
+            let hash = post.password_hash.as_deref().unwrap_or_default();
 
-    match post {
-        Ok(post) => {
-            let response = SinglePostResponse { data: post };
+            if !password.is_some_and(|password| validate_password(password, hash).is_ok()) {
+                return Err(AppError::from((
+                    StatusCode::UNAUTHORIZED,
+                    anyhow::anyhow!("Post is password-protected"),
+                )));
+            }
+        }
+    }
 
-            Ok(Json(response))
+    let http_cache = &state.settings.load().http_cache;
+    let cache_control = match (post.status, post.visibility) {
+        (PostStatus::Published, PostVisibility::Public | PostVisibility::Unlisted) => {
+            http_cache.public_for(path.as_str())
         }
-        Err(e) => Err(AppError::from((StatusCode::NOT_FOUND, e))),
+        _ => http_cache.private(),
+    };
+    let last_modified = Some(post.updated);
+
+    let response = SinglePostResponse { data: post };
+
+    conditional::respond(headers, &response, last_modified, &cache_control).map(vary_on_auth)
+}
+
+/// Responses differ between anonymous and authenticated readers, so caches
+/// must not share them.
+fn vary_on_auth(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("authorization"));
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static(POST_PASSWORD));
+    response
+}
+
+/// Characters left as they are in slugs used in a `Location` header.
+const SLUG: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+fn redirect_to_current_slug(uri: &Uri, slug: &str) -> Response {
+    let path = uri.path();
+    let by_slug = path.rsplit_once('/').map_or(path, |(by_slug, _)| by_slug);
+    let location = format!("{}/{}", by_slug, utf8_percent_encode(slug, SLUG));
+
+    (
+        StatusCode::MOVED_PERMANENTLY,
+        [(header::LOCATION, location)],
+    )
+        .into_response()
+}
+
+/// Redirects `/posts/{slug}` to `/posts/by-slug/{slug}`, keeping the
+/// percent-encoded slug and query as they were requested.
+/// Whether `slug` is the current or an old slug of a post.
+async fn is_slug(state: &ApplicationState, slug: &str) -> Result<bool, AppError> {
+    if state.post_service.get_post_by_slug(slug).await.is_ok() {
+        return Ok(true);
     }
+
+    Ok(state.post_service.find_moved_slug(slug).await?.is_some())
+}
+
+fn redirect_to_slug(uri: &Uri) -> Response {
+    let path = uri.path();
+    let (posts, slug) = path.rsplit_once('/').unwrap_or(("", path));
+    let location = match uri.query() {
+        Some(query) => format!("{}/by-slug/{}?{}", posts, slug, query),
+        None => format!("{}/by-slug/{}", posts, slug),
+    };
+
+    let mut response = Redirect::permanent(&location).into_response();
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    response
 }
 
 #[utoipa::path(
@@ -109,15 +711,84 @@ pub async fn get(
         ("id" = i64, Path, description = "ID of the post"),
     ),
     responses(
-        (status = 200, description = "Post deleted"),
+        (status = 200, description = "Post moved to the trash"),
+        (status = 404, description = "Post not found or not owned by the user", body = ErrorResponse),
     ),
 )]
 pub async fn delete(
-    Extension(_claims): Extension<TokenClaims>,
+    Extension(claims): Extension<TokenClaims>,
     State(state): State<Arc<ApplicationState>>,
     Path(id): Path<i64>,
+    audit: AuditContext,
 ) -> Result<Json<()>, AppError> {
-    state.post_service.delete_post(id).await?;
+    let post = state
+        .post_service
+        .get_post_by_id(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    if !reader(&state, Some(&claims)).await?.owns(&post) {
+        return Err(AppError::from((
+            StatusCode::NOT_FOUND,
+            anyhow::anyhow!("Post not found: {}", id),
+        )));
+    }
+
+    state.post_service.delete_post(id, &audit).await?;
 
     Ok(Json(()))
 }
+
+#[utoipa::path(
+    get,
+    path = "/trash",
+    tag = "posts",
+    responses(
+        (status = 200, description = "Deleted posts of the user, all deleted posts for editors", body = ListPostsResponse),
+    ),
+)]
+pub async fn trash(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+) -> Result<Json<ListPostsResponse>, AppError> {
+    let reader = reader(&state, Some(&claims)).await?;
+    let posts = state.post_service.get_trash(reader).await?;
+
+    Ok(Json(ListPostsResponse { data: posts }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/restore",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+    ),
+    responses(
+        (status = 200, description = "Post moved out of the trash", body = SinglePostResponse),
+        (status = 404, description = "Post not in the trash of the user", body = ErrorResponse),
+    ),
+)]
+pub async fn restore(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    audit: AuditContext,
+) -> Result<Response, AppError> {
+    let post = state
+        .post_service
+        .get_deleted_post(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    if !reader(&state, Some(&claims)).await?.owns(&post) {
+        return Err(AppError::from((
+            StatusCode::NOT_FOUND,
+            anyhow::anyhow!("Post not found in trash: {}", id),
+        )));
+    }
+
+    let post = state.post_service.restore_post(id, &audit).await?;
+
+    conditional::with_etag(StatusCode::OK, &SinglePostResponse { data: post })
+}
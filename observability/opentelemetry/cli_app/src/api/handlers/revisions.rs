@@ -0,0 +1,162 @@
+use crate::api::conditional;
+use crate::api::errors::AppError;
+use crate::api::handlers::posts::reader;
+use crate::api::request::revisions::{DiffFormat, RevisionQuery};
+use crate::api::response::posts::SinglePostResponse;
+use crate::api::response::revisions::{ListRevisionsResponse, SingleRevisionResponse};
+use crate::api::response::TokenClaims;
+use crate::model::Post;
+use crate::services::audit::AuditContext;
+use crate::services::post::PostService;
+use crate::state::ApplicationState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::{Extension, Json};
+use similar::{ChangeTag, TextDiff};
+use std::sync::Arc;
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+    ),
+    responses(
+        (status = 200, description = "Revisions of the post, newest first", body = ListRevisionsResponse),
+        (status = 404, description = "Post not found or not owned by the user", body = ErrorResponse),
+    ),
+)]
+pub async fn list(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<ListRevisionsResponse>, AppError> {
+    owned_post(&state, &claims, id).await?;
+
+    let revisions = state.post_service.get_revisions(id).await?;
+
+    Ok(Json(ListRevisionsResponse { data: revisions }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions/{revision}",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+        ("revision" = i64, Path, description = "Revision of the post"),
+        RevisionQuery,
+    ),
+    responses(
+        (status = 200, description = "Revision with the changes of title and content to the current version", body = SingleRevisionResponse),
+        (status = 404, description = "Post or revision not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, revision)): Path<(i64, i64)>,
+    Query(query): Query<RevisionQuery>,
+) -> Result<Json<SingleRevisionResponse>, AppError> {
+    let post = owned_post(&state, &claims, id).await?;
+
+    let revision = state
+        .post_service
+        .get_revision(id, revision)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    let diff = diff(
+        &document(&revision.title, &revision.content),
+        &document(&post.title, &post.content),
+        revision.revision,
+        query.diff,
+    );
+
+    Ok(Json(SingleRevisionResponse {
+        data: revision,
+        diff,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/revisions/{revision}/restore",
+    tag = "posts",
+    params(
+        ("id" = i64, Path, description = "ID of the post"),
+        ("revision" = i64, Path, description = "Revision to restore"),
+    ),
+    responses(
+        (status = 200, description = "Post with the title and content of the revision", body = SinglePostResponse),
+        (status = 404, description = "Post or revision not found", body = ErrorResponse),
+    ),
+)]
+pub async fn restore(
+    Extension(claims): Extension<TokenClaims>,
+    State(state): State<Arc<ApplicationState>>,
+    Path((id, revision)): Path<(i64, i64)>,
+    audit: AuditContext,
+) -> Result<Response, AppError> {
+    owned_post(&state, &claims, id).await?;
+
+    state
+        .post_service
+        .get_revision(id, revision)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    let post = state
+        .post_service
+        .restore_revision(id, revision, &audit)
+        .await?;
+
+    conditional::with_etag(StatusCode::OK, &SinglePostResponse { data: post })
+}
+
+/// The history of a post is only shown to its author and editors; for
+/// everybody else the post does not exist.
+async fn owned_post(
+    state: &ApplicationState,
+    claims: &TokenClaims,
+    id: i64,
+) -> Result<Post, AppError> {
+    let post = state
+        .post_service
+        .get_post_by_id(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    match reader(state, Some(claims)).await?.owns(&post) {
+        true => Ok(post),
+        false => Err(AppError::from((
+            StatusCode::NOT_FOUND,
+            anyhow::anyhow!("Post not found: {}", id),
+        ))),
+    }
+}
+
+/// The text revisions are compared by, so a changed title shows up in the
+/// diff as a changed first line.
+fn document(title: &str, content: &str) -> String {
+    format!("{}\n\n{}", title, content)
+}
+
+fn diff(old: &str, new: &str, revision: i64, format: DiffFormat) -> String {
+    match format {
+        DiffFormat::Unified => TextDiff::from_lines(old, new)
+            .unified_diff()
+            .header(&format!("revision {}", revision), "current")
+            .to_string(),
+        DiffFormat::Word => TextDiff::from_words(old, new)
+            .iter_all_changes()
+            .map(|change| match change.tag() {
+                ChangeTag::Equal => change.value().to_string(),
+                ChangeTag::Delete => format!("[-{}-]", change.value()),
+                ChangeTag::Insert => format!("{{+{}+}}", change.value()),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,95 @@
+use crate::api::errors::AppError;
+use crate::api::patch::{self, PatchFormat};
+use crate::api::response::users::{SingleUserResponse, UserView};
+use crate::model::encrypt_password;
+use crate::services::audit::AuditContext;
+use crate::services::user::{PatchUserRequest, UserService, UsernameTaken};
+use crate::state::ApplicationState;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use std::sync::Arc;
+
+/// Fields of a user a patch may change, all others are read-only.
+const PATCHABLE_FIELDS: [&str; 3] = ["username", "status", "role"];
+
+/// Fields a patch may set that are never part of a user in responses.
+const WRITE_ONLY_FIELDS: [&str; 1] = ["password"];
+
+#[utoipa::path(
+    patch,
+    path = "/users/{id}",
+    params(
+        ("id" = i64, Path, description = "ID of the user"),
+    ),
+    tag = "users",
+    request_body(
+        content = Object,
+        content_type = "application/merge-patch+json",
+        description = "JSON Merge Patch (RFC 7386) of the user, or a JSON Patch (RFC 6902) sent as application/json-patch+json",
+    ),
+    responses(
+        (status = 200, description = "Patched user", body = SingleUserResponse),
+        (status = 403, description = "Administrator role required"),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 409, description = "A test operation failed or username is already taken", body = ErrorResponse),
+        (status = 415, description = "Unsupported patch format", body = ErrorResponse),
+        (status = 422, description = "Invalid patch or read-only field changed", body = ErrorResponse),
+    ),
+)]
+pub async fn patch(
+    State(state): State<Arc<ApplicationState>>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    audit: AuditContext,
+    body: Bytes,
+) -> Result<Json<SingleUserResponse>, AppError> {
+    let format = PatchFormat::from_headers(&headers)?;
+
+    let current = state
+        .user_service
+        .get_user_by_id(id)
+        .await
+        .map_err(|e| AppError::from((StatusCode::NOT_FOUND, e)))?;
+
+    // The patch is applied to the user as it is returned by the API, the
+    // changed fields are then written back.
+    let before = serde_json::to_value(UserView::from(current)).map_err(anyhow::Error::from)?;
+    let after = format.apply(&before, &body)?;
+
+    patch::check_read_only(&before, &after, &PATCHABLE_FIELDS, &WRITE_ONLY_FIELDS)?;
+
+    let password: Option<String> = patch::changed(&before, &after, "password")?;
+    let req = PatchUserRequest {
+        username: patch::changed(&before, &after, "username")?,
+        password: password
+            .map(|password| encrypt_password(&password))
+            .transpose()?,
+        status: patch::changed(&before, &after, "status")?,
+        role: patch::changed(&before, &after, "role")?,
+    };
+
+    if req.username.as_deref().is_some_and(str::is_empty) {
+        return Err(AppError::from((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            anyhow::anyhow!("Username must not be empty"),
+        )));
+    }
+
+    let user = match req.is_empty() {
+        true => state.user_service.get_user_by_id(id).await?,
+        false => state
+            .user_service
+            .patch_user(id, req, &audit)
+            .await
+            .map_err(|e| match e.is::<UsernameTaken>() {
+                true => AppError::from((StatusCode::CONFLICT, e)),
+                false => e.into(),
+            })?,
+    };
+
+    Ok(Json(SingleUserResponse {
+        data: UserView::from(user),
+    }))
+}
@@ -3,23 +3,87 @@ use std::sync::Arc;
 use axum::body::Body;
 use axum::{
     extract::State,
-    http::{header, Request, StatusCode},
+    http::{header, HeaderMap, Request, StatusCode},
     middleware::Next,
     response::IntoResponse,
+    Extension,
 };
 
 use crate::api::errors::AppError;
 use crate::api::response::TokenClaims;
+use crate::model::UserRole;
+use crate::server::tls::ClientCertificate;
 use crate::state::ApplicationState;
 use jsonwebtoken::{decode, DecodingKey, Validation};
 
+/// Which credentials `auth` accepts on a route. Routes without an explicit
+/// policy, set as an `Extension` outside of `auth`, require a bearer token.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AuthPolicy {
+    #[default]
+    BearerToken,
+    BearerTokenOrClientCertificate,
+}
+
 pub async fn auth(
     State(state): State<Arc<ApplicationState>>,
     mut req: Request<Body>,
     next: Next,
 ) -> Result<impl IntoResponse, AppError> {
-    let token = req
-        .headers()
+    let policy = req
+        .extensions()
+        .get::<AuthPolicy>()
+        .copied()
+        .unwrap_or_default();
+    let client_cert = req.extensions().get::<ClientCertificate>();
+
+    let claims = match (policy, client_cert) {
+        (AuthPolicy::BearerTokenOrClientCertificate, Some(client_cert))
+            if !req.headers().contains_key(header::AUTHORIZATION) =>
+        {
+            client_cert_claims(&state, client_cert)?
+        }
+        _ => decode_claims(&state, req.headers())?,
+    };
+
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+/// Builds claims for the principal that the client certificate is mapped to
+/// in `server.tls.client_principals`.
+fn client_cert_claims(
+    state: &ApplicationState,
+    client_cert: &ClientCertificate,
+) -> Result<TokenClaims, AppError> {
+    let settings = state.settings.load();
+    let principal = settings
+        .server
+        .tls
+        .as_ref()
+        .and_then(|tls| client_cert.principal(tls))
+        .ok_or_else(|| {
+            AppError::from((
+                StatusCode::UNAUTHORIZED,
+                anyhow::anyhow!("Client certificate is not trusted: {}", client_cert.subject),
+            ))
+        })?;
+
+    let now = chrono::Utc::now().timestamp() as usize;
+
+    Ok(TokenClaims {
+        sub: principal.name.clone(),
+        role: principal.role,
+        iat: now,
+        exp: now,
+    })
+}
+
+pub fn decode_claims(
+    state: &ApplicationState,
+    headers: &HeaderMap,
+) -> Result<TokenClaims, AppError> {
+    let token = headers
         .get(header::AUTHORIZATION)
         .and_then(|auth_header| auth_header.to_str().ok())
         .and_then(|auth_value| {
@@ -55,6 +119,20 @@ pub async fn auth(
     })?
     .claims;
 
-    req.extensions_mut().insert(claims);
+    Ok(claims)
+}
+
+pub async fn admin(
+    Extension(claims): Extension<TokenClaims>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, AppError> {
+    if claims.role != UserRole::Admin {
+        return Err(AppError::from((
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Administrator role required"),
+        )));
+    }
+
     Ok(next.run(req).await)
 }
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderName, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::level_filters::LevelFilter;
+
+use crate::api::middleware::auth::decode_claims;
+use crate::logging;
+use crate::model::UserRole;
+use crate::state::ApplicationState;
+
+pub static DEBUG_LOG_HEADER: HeaderName = HeaderName::from_static("x-debug-log");
+
+/// Raises the log level for a single request, e.g. `X-Debug-Log: trace`.
+/// The header is only honored together with an administrator token.
+pub async fn debug_log(
+    State(state): State<Arc<ApplicationState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    match requested_level(&state, req.headers()) {
+        Some(level) => {
+            tracing::info!(%level, "Debug logging requested");
+            logging::debug_request(level, next.run(req)).await
+        }
+        None => {
+            if req.headers().contains_key(&DEBUG_LOG_HEADER)
+                && state.settings.load().logging.allow_debug_header
+            {
+                tracing::warn!("Ignoring X-Debug-Log header without an administrator token");
+            }
+            next.run(req).await
+        }
+    }
+}
+
+/// The level a request asks for, if the header is enabled and comes with an
+/// administrator token. Also used when the request span is created.
+pub fn requested_level(state: &ApplicationState, headers: &HeaderMap) -> Option<LevelFilter> {
+    let level = headers
+        .get(&DEBUG_LOG_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<LevelFilter>().ok())?;
+
+    if !state.settings.load().logging.allow_debug_header {
+        return None;
+    }
+
+    match decode_claims(state, headers) {
+        Ok(claims) if claims.role == UserRole::Admin => Some(level),
+        _ => None,
+    }
+}
@@ -0,0 +1,123 @@
+use axum::extract::Request;
+use axum::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// The content encodings of the compression layer.
+const ENCODINGS: [&str; 3] = ["gzip", "br", "zstd"];
+
+/// Keeps the strong `ETag`s of `api::conditional` unique per representation.
+/// Handlers tag the uncompressed JSON; this layer, which sits outside the
+/// compression layer, appends the content encoding, e.g. `"<hash>-gzip"`,
+/// and strips it from the tags of `If-None-Match` and `If-Match` again, so
+/// handlers only ever see their own tags.
+pub async fn encoding_etag(mut req: Request, next: Next) -> Response {
+    let if_none_match = header_str(req.headers(), &header::IF_NONE_MATCH);
+    for name in [header::IF_NONE_MATCH, header::IF_MATCH] {
+        strip_header(req.headers_mut(), name);
+    }
+
+    let mut response = next.run(req).await;
+
+    let Some(etag) = header_str(response.headers(), &header::ETAG) else {
+        return response;
+    };
+    let encoding = header_str(response.headers(), &header::CONTENT_ENCODING);
+
+    let tagged = match encoding {
+        Some(encoding) => with_encoding(&etag, &encoding),
+        // A 304 has no body to tell the encoding by, so it carries the tag
+        // the client sent for its cached representation.
+        None if response.status() == StatusCode::NOT_MODIFIED => if_none_match
+            .as_deref()
+            .and_then(|list| tags(list).find(|tag| strip_encoding(tag) == etag))
+            .map(str::to_string),
+        None => None,
+    };
+
+    let headers = response.headers_mut();
+    if let Some(value) = tagged.and_then(|tag| HeaderValue::from_str(&tag).ok()) {
+        headers.insert(header::ETAG, value);
+    }
+    let varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.to_ascii_lowercase().contains("accept-encoding"));
+    if !varies {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    response
+}
+
+fn header_str(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn strip_header(headers: &mut HeaderMap, name: HeaderName) {
+    let Some(list) = header_str(headers, &name) else {
+        return;
+    };
+    let stripped = tags(&list)
+        .map(strip_encoding)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if let Ok(value) = HeaderValue::from_str(&stripped) {
+        headers.insert(name, value);
+    }
+}
+
+fn tags(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim)
+}
+
+/// `"<hash>"` to `"<hash>-<encoding>"`; weak tags are left alone.
+fn with_encoding(etag: &str, encoding: &str) -> Option<String> {
+    let opaque = etag.strip_prefix('"')?.strip_suffix('"')?;
+
+    Some(format!("\"{}-{}\"", opaque, encoding))
+}
+
+/// Removes the encoding `with_encoding` appended, keeping a `W/` prefix.
+fn strip_encoding(tag: &str) -> String {
+    ENCODINGS
+        .iter()
+        .find_map(|encoding| {
+            let opaque = tag
+                .strip_suffix('"')?
+                .strip_suffix(encoding)?
+                .strip_suffix('-')?;
+            Some(format!("{}\"", opaque))
+        })
+        .unwrap_or_else(|| tag.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_encoding, with_encoding};
+
+    #[test]
+    fn appends_encoding_to_strong_tags() {
+        assert_eq!(
+            with_encoding("\"abc\"", "gzip").as_deref(),
+            Some("\"abc-gzip\"")
+        );
+        assert_eq!(with_encoding("W/\"abc\"", "gzip"), None);
+    }
+
+    #[test]
+    fn strips_known_encodings() {
+        assert_eq!(strip_encoding("\"abc-gzip\""), "\"abc\"");
+        assert_eq!(strip_encoding("\"abc-br\""), "\"abc\"");
+        assert_eq!(strip_encoding("W/\"abc-zstd\""), "W/\"abc\"");
+        assert_eq!(strip_encoding("\"abc\""), "\"abc\"");
+        assert_eq!(strip_encoding("\"abcgzip\""), "\"abcgzip\"");
+        assert_eq!(strip_encoding("*"), "*");
+    }
+}
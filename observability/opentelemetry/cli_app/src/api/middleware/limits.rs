@@ -0,0 +1,100 @@
+use crate::api::errors::AppError;
+use crate::settings::Server;
+use crate::state::ApplicationState;
+use axum::body::Body;
+use axum::extract::{MatchedPath, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use http_body_util::LengthLimitError;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_REQUEST_TIMEOUT_SECONDS: u64 = 30;
+const DEFAULT_BODY_LIMIT_BYTES: usize = 2 * 1024 * 1024;
+
+/// Answers with 408 when a request, including reading its body, takes longer
+/// than `server.request_timeout_seconds`.
+pub async fn timeout(
+    State(state): State<Arc<ApplicationState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let timeout = Duration::from_secs(
+        state
+            .settings
+            .load()
+            .server
+            .request_timeout_seconds
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECONDS),
+    );
+
+    tokio::time::timeout(timeout, next.run(req))
+        .await
+        .map_err(|_| {
+            AppError::from((
+                StatusCode::REQUEST_TIMEOUT,
+                anyhow::anyhow!("Request timed out after {} seconds", timeout.as_secs()),
+            ))
+        })
+}
+
+/// Reads the whole body up front and answers with 413 if it is larger than
+/// the limit of the route, or `server.body_limit_bytes` for other routes.
+pub async fn body_limit(
+    State(state): State<Arc<ApplicationState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let limit = {
+        let settings = state.settings.load();
+        req.extensions()
+            .get::<MatchedPath>()
+            .and_then(|path| settings.server.route_body_limits.get(path.as_str()))
+            .copied()
+            .or(settings.server.body_limit_bytes)
+            .unwrap_or(DEFAULT_BODY_LIMIT_BYTES)
+    };
+
+    let too_large = || {
+        AppError::from((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            anyhow::anyhow!("Request body is larger than {} bytes", limit),
+        ))
+    };
+
+    let content_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > limit) {
+        return Err(too_large());
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = axum::body::to_bytes(body, limit).await.map_err(|e| {
+        let e = e.into_inner();
+        if e.is::<LengthLimitError>() {
+            too_large()
+        } else {
+            AppError::from((
+                StatusCode::BAD_REQUEST,
+                anyhow::anyhow!(e).context("Failed to read request body"),
+            ))
+        }
+    })?;
+
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
+
+/// The largest body any route accepts, for transports that have to buffer
+/// bodies before the router sees them.
+pub fn largest_body_limit(server: &Server) -> usize {
+    server.route_body_limits.values().copied().fold(
+        server.body_limit_bytes.unwrap_or(DEFAULT_BODY_LIMIT_BYTES),
+        usize::max,
+    )
+}
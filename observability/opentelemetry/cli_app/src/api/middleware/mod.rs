@@ -1 +1,5 @@
 pub mod auth;
+pub mod debug_log;
+pub mod etag;
+pub mod limits;
+pub mod request_id;
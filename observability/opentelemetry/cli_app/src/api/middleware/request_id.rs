@@ -0,0 +1,64 @@
+use axum::body::Body;
+use axum::{
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::future::Future;
+use uuid::Uuid;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// The ID of the request being handled by the current task, for code that
+    /// has no access to the request itself, e.g. error responses, audit
+    /// records or calls to other services.
+    pub fn current() -> Option<RequestId> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+
+    /// Runs `future` with the given ID as the current request ID, e.g. for
+    /// background work started on behalf of a request.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_REQUEST_ID.scope(self, future).await
+    }
+}
+
+/// Accepts the caller's `X-Request-Id` or generates a UUIDv7, records it on
+/// the `http_request` span and echoes it in the response.
+pub async fn request_id(mut req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid(value))
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::now_v7().to_string());
+
+    tracing::Span::current().record("request_id", request_id.as_str());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let mut response = RequestId(request_id.clone()).scope(next.run(req)).await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(&REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+/// Client supplied IDs end up in logs, so only short, plain values are kept.
+fn is_valid(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
@@ -1,39 +1,92 @@
+use crate::settings::{Compression, ContentEncoding};
 use crate::state::ApplicationState;
-use axum::extract::MatchedPath;
-use axum::http::Request;
-use axum::Router;
+use axum::extract::{DefaultBodyLimit, MatchedPath};
+use axum::http::{header, Extensions, HeaderMap, Request, StatusCode, Version};
+use axum::{middleware as axum_middleware, Router};
 use std::sync::Arc;
+use tower_http::compression::predicate::{Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::decompression::RequestDecompressionLayer;
 use tower_http::trace::TraceLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+mod admin;
+pub mod conditional;
 pub mod errors;
 mod handlers;
 pub mod middleware;
+pub mod patch;
 pub mod request;
 pub mod response;
 mod v1;
 
+/// Compresses responses of the configured content types once they are larger
+/// than `min_size_bytes`. Disabling it turns off every algorithm.
+fn compression(settings: &Compression) -> CompressionLayer<impl Predicate> {
+    let content_types = settings.content_types.clone();
+    let predicate = SizeAbove::new(settings.min_size_bytes.unwrap_or(1024)).and(
+        move |_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions| {
+            headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| content_types.iter().any(|ct| value.starts_with(ct)))
+        },
+    );
+
+    let enabled = |encoding| settings.enabled && settings.algorithms.contains(&encoding);
+
+    CompressionLayer::new()
+        .gzip(enabled(ContentEncoding::Gzip))
+        .br(enabled(ContentEncoding::Br))
+        .zstd(enabled(ContentEncoding::Zstd))
+        .compress_when(predicate)
+}
+
 pub fn configure(state: Arc<ApplicationState>) -> Router {
     Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url(
             "/v1/api-docs/openapi.json",
             crate::api::v1::ApiDoc::openapi(),
         ))
-        .nest("/v1", v1::configure(state))
+        .nest("/v1", v1::configure(state.clone()))
+        .nest("/admin", admin::configure(state.clone()))
+        // Bodies are limited by `middleware::limits::body_limit` instead.
+        .layer(DefaultBodyLimit::disable())
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            middleware::limits::body_limit,
+        ))
+        .layer(RequestDecompressionLayer::new())
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            middleware::limits::timeout,
+        ))
+        .layer(compression(&state.settings.load().server.compression))
+        .layer(axum_middleware::from_fn(middleware::etag::encoding_etag))
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            middleware::debug_log::debug_log,
+        ))
+        .layer(axum_middleware::from_fn(middleware::request_id::request_id))
         .layer(
-            TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
+            TraceLayer::new_for_http().make_span_with(move |request: &Request<_>| {
                 // Log the matched route's path (with placeholders not filled in).
                 // Use request.uri() or OriginalUri if you want the real path.
                 let matched_path = request
                     .extensions()
                     .get::<MatchedPath>()
                     .map(MatchedPath::as_str);
+                // Set when the span is created, as the sampler decides then.
+                let debug_log = middleware::debug_log::requested_level(&state, request.headers())
+                    .map(|level| level.to_string());
 
                 tracing::info_span!(
                     "http_request",
                     method = ?request.method(),
                     matched_path,
+                    request_id = tracing::field::Empty,
+                    debug_log = debug_log.as_deref(),
                 )
             }),
         )
@@ -0,0 +1,121 @@
+use crate::api::errors::AppError;
+use axum::http::{header, HeaderMap, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+const MERGE_PATCH: &str = "application/merge-patch+json";
+const JSON_PATCH: &str = "application/json-patch+json";
+
+/// The patch formats accepted by `PATCH` endpoints.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// JSON Merge Patch, RFC 7386.
+    MergePatch,
+    /// JSON Patch, RFC 6902.
+    JsonPatch,
+}
+
+impl PatchFormat {
+    /// Picks the format by the `Content-Type` of the request, other types are
+    /// rejected with 415.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self, AppError> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match content_type.as_str() {
+            MERGE_PATCH => Ok(PatchFormat::MergePatch),
+            JSON_PATCH => Ok(PatchFormat::JsonPatch),
+            _ => Err(AppError::from((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                anyhow::anyhow!("Expected {} or {}", MERGE_PATCH, JSON_PATCH),
+            ))),
+        }
+    }
+
+    /// Applies the patch in `body` to a copy of `before`. A failed JSON Patch
+    /// operation, e.g. a `test`, is a conflict.
+    pub fn apply(self, before: &Value, body: &[u8]) -> Result<Value, AppError> {
+        let mut after = before.clone();
+
+        match self {
+            PatchFormat::MergePatch => {
+                let patch: Value = serde_json::from_slice(body).map_err(unprocessable)?;
+                json_patch::merge(&mut after, &patch);
+            }
+            PatchFormat::JsonPatch => {
+                let patch: json_patch::Patch =
+                    serde_json::from_slice(body).map_err(unprocessable)?;
+                json_patch::patch(&mut after, &patch)
+                    .map_err(|e| AppError::from((StatusCode::CONFLICT, anyhow::anyhow!(e))))?;
+            }
+        }
+
+        Ok(after)
+    }
+}
+
+fn unprocessable(e: serde_json::Error) -> AppError {
+    AppError::from((
+        StatusCode::UNPROCESSABLE_ENTITY,
+        anyhow::anyhow!("Invalid patch: {}", e),
+    ))
+}
+
+/// Rejects patches that add unknown fields or touch read-only ones. Only
+/// `patchable` fields may change; `write_only` fields are never part of a
+/// response, so a patch may add them.
+pub fn check_read_only(
+    before: &Value,
+    after: &Value,
+    patchable: &[&str],
+    write_only: &[&str],
+) -> Result<(), AppError> {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return Err(AppError::from((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            anyhow::anyhow!("Patch must result in an object"),
+        )));
+    };
+
+    for key in before.keys().chain(after.keys()) {
+        let error = match (before.get(key), after.get(key)) {
+            (None, Some(_)) if write_only.contains(&key.as_str()) => continue,
+            (None, _) => format!("Unknown field {}", key),
+            // A merge patch removes the fields it sets to null.
+            (Some(old), new) if old == new.unwrap_or(&Value::Null) => continue,
+            _ if patchable.contains(&key.as_str()) => continue,
+            _ => format!("Field {} is read-only", key),
+        };
+
+        return Err(AppError::from((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            anyhow::anyhow!(error),
+        )));
+    }
+
+    Ok(())
+}
+
+/// The new value of `field` if the patch changed it.
+pub fn changed<T: DeserializeOwned>(
+    before: &Value,
+    after: &Value,
+    field: &str,
+) -> Result<Option<T>, AppError> {
+    let old = before.get(field).unwrap_or(&Value::Null);
+    let new = after.get(field).unwrap_or(&Value::Null);
+    if old == new {
+        return Ok(None);
+    }
+
+    serde_json::from_value(new.clone()).map(Some).map_err(|e| {
+        AppError::from((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            anyhow::anyhow!("Invalid value for {}: {}", field, e),
+        ))
+    })
+}
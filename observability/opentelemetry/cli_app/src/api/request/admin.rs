@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateLogLevelRequest {
+    pub level: String,
+    pub ttl_seconds: Option<u64>,
+}
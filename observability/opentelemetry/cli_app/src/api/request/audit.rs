@@ -0,0 +1,32 @@
+use crate::api::middleware::request_id::RequestId;
+use crate::api::response::TokenClaims;
+use crate::services::audit::AuditContext;
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+/// Collects the actor, client address and request ID for audit records. The
+/// actor is only known on routes behind the `auth` middleware.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(AuditContext {
+            actor: parts
+                .extensions
+                .get::<TokenClaims>()
+                .map(|claims| claims.sub.clone()),
+            ip: parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string()),
+            request_id: parts
+                .extensions
+                .get::<RequestId>()
+                .map(|RequestId(id)| id.clone()),
+        })
+    }
+}
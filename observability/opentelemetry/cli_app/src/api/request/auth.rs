@@ -0,0 +1,34 @@
+use crate::api::errors::AppError;
+use crate::api::middleware::auth::decode_claims;
+use crate::api::response::TokenClaims;
+use crate::state::ApplicationState;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header;
+use axum::http::request::Parts;
+use std::sync::Arc;
+
+/// The claims of the bearer token, for routes that are open to anonymous
+/// requests. Unlike the `auth` middleware, a missing token is accepted, but
+/// an invalid one is still rejected.
+pub struct OptionalClaims(pub Option<TokenClaims>);
+
+#[async_trait]
+impl FromRequestParts<Arc<ApplicationState>> for OptionalClaims {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<ApplicationState>,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(claims) = parts.extensions.get::<TokenClaims>() {
+            return Ok(Self(Some(claims.clone())));
+        }
+
+        if !parts.headers.contains_key(header::AUTHORIZATION) {
+            return Ok(Self(None));
+        }
+
+        decode_claims(state, &parts.headers).map(|claims| Self(Some(claims)))
+    }
+}
@@ -1 +1,6 @@
+pub mod admin;
+pub mod audit;
+pub mod auth;
 pub mod login;
+pub mod posts;
+pub mod revisions;
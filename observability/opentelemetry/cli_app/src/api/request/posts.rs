@@ -0,0 +1,21 @@
+use crate::model::PostStatus;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListPostsQuery {
+    /// Only posts with this status. Drafts are only listed for
+    /// authenticated readers.
+    pub status: Option<PostStatus>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ScheduleQuery {
+    /// Start of the period, now by default.
+    pub from: Option<DateTime<Utc>>,
+    /// End of the period, 30 days after its start by default.
+    pub to: Option<DateTime<Utc>>,
+}
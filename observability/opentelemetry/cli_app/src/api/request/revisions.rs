@@ -0,0 +1,20 @@
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+/// How a revision is compared with the current version of the post.
+#[derive(Clone, Copy, Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffFormat {
+    /// Changed lines, like `diff -u`.
+    #[default]
+    Unified,
+    /// Changed words, marked as `[-removed-]` and `{+added+}`.
+    Word,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RevisionQuery {
+    #[serde(default)]
+    pub diff: DiffFormat,
+}
@@ -0,0 +1,8 @@
+use crate::logging::LogLevel;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct LogLevelResponse {
+    pub data: LogLevel,
+}
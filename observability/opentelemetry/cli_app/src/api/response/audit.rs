@@ -0,0 +1,8 @@
+use crate::model::AuditEvent;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct ListAuditEventsResponse {
+    pub data: Vec<AuditEvent>,
+}
@@ -0,0 +1,9 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub status: String,
+    pub message: String,
+    pub request_id: Option<String>,
+}
@@ -1,11 +1,18 @@
+pub mod admin;
+pub mod audit;
+pub mod error;
 pub mod login;
 pub mod posts;
+pub mod revisions;
+pub mod users;
 
+use crate::model::UserRole;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenClaims {
     pub sub: String,
+    pub role: UserRole,
     pub iat: usize,
     pub exp: usize,
 }
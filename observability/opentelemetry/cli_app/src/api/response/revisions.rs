@@ -0,0 +1,17 @@
+use crate::model::PostRevision;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct ListRevisionsResponse {
+    pub data: Vec<PostRevision>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SingleRevisionResponse {
+    pub data: PostRevision,
+    /// Changes of the title and content from this revision to the current
+    /// version. The title is compared as the first line, followed by an
+    /// empty line and the content.
+    pub diff: String,
+}
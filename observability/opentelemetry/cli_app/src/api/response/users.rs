@@ -0,0 +1,35 @@
+use crate::model::{User, UserRole, UserStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// A user as returned by the API, without the password hash.
+#[derive(Serialize, ToSchema)]
+pub struct UserView {
+    pub id: i64,
+    pub username: String,
+    pub status: UserStatus,
+    pub role: UserRole,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub last_login: Option<DateTime<Utc>>,
+}
+
+impl From<User> for UserView {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            status: user.status,
+            role: user.role,
+            created: user.created,
+            updated: user.updated,
+            last_login: user.last_login,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SingleUserResponse {
+    pub data: UserView,
+}
@@ -1,8 +1,8 @@
 use super::handlers;
-use crate::api::middleware::auth::auth;
+use crate::api::middleware::auth::{admin, auth, AuthPolicy};
 use crate::state::ApplicationState;
-use axum::routing::{delete, get, post, put};
-use axum::{middleware, Router};
+use axum::routing::{delete, get, patch, post, put};
+use axum::{middleware, Extension, Router};
 use std::sync::Arc;
 
 pub fn configure(state: Arc<ApplicationState>) -> Router {
@@ -25,18 +25,98 @@ pub fn configure(state: Arc<ApplicationState>) -> Router {
             "/posts/:id",
             get(handlers::posts::get).with_state(state.clone()),
         )
+        .route(
+            "/posts/by-slug/:slug",
+            get(handlers::posts::get_by_slug).with_state(state.clone()),
+        )
         .route(
             "/posts/:id",
             put(handlers::posts::update)
                 .with_state(state.clone())
                 .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
         )
+        .route(
+            "/posts/:id",
+            patch(handlers::posts::patch)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
         .route(
             "/posts/:id",
             delete(handlers::posts::delete)
                 .with_state(state.clone())
                 .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
         )
+        .route(
+            "/posts/schedule",
+            get(handlers::posts::schedule)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/submit",
+            post(handlers::posts::submit)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/approve",
+            post(handlers::posts::approve)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/reject",
+            post(handlers::posts::reject)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/restore",
+            post(handlers::posts::restore)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/trash",
+            get(handlers::posts::trash)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/revisions",
+            get(handlers::revisions::list)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/revisions/:revision",
+            get(handlers::revisions::get)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/posts/:id/revisions/:revision/restore",
+            post(handlers::revisions::restore)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth)),
+        )
+        .route(
+            "/audit",
+            get(handlers::audit::list)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn(admin))
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+                .route_layer(Extension(AuthPolicy::BearerTokenOrClientCertificate)),
+        )
+        .route(
+            "/users/:id",
+            patch(handlers::users::patch)
+                .with_state(state.clone())
+                .route_layer(middleware::from_fn(admin))
+                .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+                .route_layer(Extension(AuthPolicy::BearerTokenOrClientCertificate)),
+        )
         .route("/login", post(handlers::login::login).with_state(state))
 }
 
@@ -49,26 +129,54 @@ use utoipa::OpenApi;
         handlers::login::login,
         handlers::posts::create,
         handlers::posts::update,
+        handlers::posts::patch,
         handlers::posts::delete,
         handlers::posts::list,
         handlers::posts::get,
+        handlers::posts::get_by_slug,
+        handlers::posts::schedule,
+        handlers::posts::submit,
+        handlers::posts::approve,
+        handlers::posts::reject,
+        handlers::posts::trash,
+        handlers::posts::restore,
+        handlers::revisions::list,
+        handlers::revisions::get,
+        handlers::revisions::restore,
+        handlers::audit::list,
+        handlers::users::patch,
     ),
     components(
         schemas(
             crate::api::request::login::LoginRequest,
             crate::api::response::login::LoginResponse,
+            crate::api::response::error::ErrorResponse,
             crate::services::post::CreatePostRequest,
             crate::services::post::UpdatePostRequest,
+            crate::services::post::RejectPostRequest,
             crate::api::response::posts::ListPostsResponse,
             crate::api::response::posts::SinglePostResponse,
             crate::model::Post,
             crate::model::PostStatus,
+            crate::model::PostVisibility,
+            crate::api::request::revisions::DiffFormat,
+            crate::api::response::revisions::ListRevisionsResponse,
+            crate::api::response::revisions::SingleRevisionResponse,
+            crate::model::PostRevision,
+            crate::api::response::audit::ListAuditEventsResponse,
+            crate::model::AuditEvent,
+            crate::api::response::users::SingleUserResponse,
+            crate::api::response::users::UserView,
+            crate::model::UserStatus,
+            crate::model::UserRole,
         ),
     ),
     tags(
         (name = "hello", description = "Hello"),
         (name = "login", description = "Login"),
         (name = "posts", description = "Posts"),
+        (name = "audit", description = "Audit log"),
+        (name = "users", description = "Users"),
     ),
     servers(
         (url = "/v1", description = "Local server"),
@@ -0,0 +1,141 @@
+use crate::services::audit::{AuditFilter, AuditService, ChainVerifier, MySQLAuditService};
+use crate::settings::Settings;
+use chrono::{DateTime, Utc};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+pub const COMMAND_NAME: &str = "audit";
+
+const PAGE_SIZE: u32 = 1000;
+
+pub fn configure() -> Command {
+    Command::new(COMMAND_NAME)
+        .about("Inspect the audit log")
+        .subcommand(
+            Command::new("export")
+                .about("Write audit events as JSON lines")
+                .arg(filter_arg("actor", "Only events caused by this user"))
+                .arg(filter_arg(
+                    "action",
+                    "Only events with this action, e.g. post.update",
+                ))
+                .arg(filter_arg(
+                    "target-type",
+                    "Only events for this kind of object",
+                ))
+                .arg(filter_arg(
+                    "target-id",
+                    "Only events for the object with this ID",
+                ))
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("RFC3339")
+                        .help("Only events at or after this time")
+                        .value_parser(value_parser!(DateTime<Utc>)),
+                )
+                .arg(
+                    Arg::new("until")
+                        .long("until")
+                        .value_name("RFC3339")
+                        .help("Only events before this time")
+                        .value_parser(value_parser!(DateTime<Utc>)),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("File to write to instead of stdout"),
+                ),
+        )
+        .subcommand(Command::new("verify").about("Check that the audit log was not modified"))
+        .arg_required_else_help(true)
+}
+
+fn filter_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).value_name("VALUE").help(help)
+}
+
+pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
+    tokio::runtime::Handle::current().block_on(async move {
+        let db_url = settings
+            .database
+            .url
+            .clone()
+            .expect("Database URL is not set");
+        let pool = sqlx::MySqlPool::connect(&db_url).await?;
+        let service = MySQLAuditService::new(pool, &settings.database);
+
+        match matches.subcommand() {
+            Some(("export", matches)) => export(&service, matches).await,
+            Some(("verify", _)) => verify(&service).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+async fn export(service: &MySQLAuditService, matches: &ArgMatches) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match matches.get_one::<String>("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+
+    let mut filter = AuditFilter {
+        actor: matches.get_one::<String>("actor").cloned(),
+        action: matches.get_one::<String>("action").cloned(),
+        target_type: matches.get_one::<String>("target-type").cloned(),
+        target_id: matches.get_one::<String>("target-id").cloned(),
+        since: matches.get_one::<DateTime<Utc>>("since").cloned(),
+        until: matches.get_one::<DateTime<Utc>>("until").cloned(),
+        limit: Some(PAGE_SIZE),
+        offset: Some(0),
+    };
+
+    loop {
+        let events = service.list_events(&filter).await?;
+        for event in &events {
+            serde_json::to_writer(&mut out, event)?;
+            out.write_all(b"\n")?;
+        }
+
+        if events.len() < PAGE_SIZE as usize {
+            break;
+        }
+        filter.offset = filter.offset.map(|offset| offset + PAGE_SIZE);
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+async fn verify(service: &MySQLAuditService) -> anyhow::Result<()> {
+    // Read first, so every event up to the head is in the pages below.
+    let mut verifier = ChainVerifier::new(service.chain_head().await?);
+    let mut filter = AuditFilter {
+        limit: Some(PAGE_SIZE),
+        offset: Some(0),
+        ..Default::default()
+    };
+
+    loop {
+        let page = service.list_events(&filter).await?;
+        for event in &page {
+            if let Err(id) = verifier.check(event) {
+                anyhow::bail!("Audit log was modified at event {}", id);
+            }
+        }
+
+        if page.len() < PAGE_SIZE as usize {
+            break;
+        }
+        filter.offset = filter.offset.map(|offset| offset + PAGE_SIZE);
+    }
+
+    let events = verifier.finish()?;
+    println!("Audit log intact: {} events", events);
+
+    Ok(())
+}
@@ -1,21 +1,37 @@
+mod audit;
 mod hello;
+mod purge;
 mod serve;
 
+use crate::logging;
 use crate::settings::Settings;
 use clap::{ArgMatches, Command};
 
 pub fn configure(command: Command) -> Command {
     command
         .subcommand(hello::configure())
+        .subcommand(audit::configure())
+        .subcommand(purge::configure())
         .subcommand(serve::configure())
         .arg_required_else_help(true)
 }
 
 pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
+    // A single runtime and subscriber are shared by every subcommand. The
+    // logging guard is dropped first so pending spans are flushed while the
+    // runtime is still alive.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let _runtime_guard = runtime.enter();
+    let logging_guard = logging::init(settings)?;
+
     if let Some((cmd, matches)) = matches.subcommand() {
         match cmd {
             hello::COMMAND_NAME => hello::handle(matches, settings)?,
-            serve::COMMAND_NAME => serve::handle(matches, settings)?,
+            audit::COMMAND_NAME => audit::handle(matches, settings)?,
+            purge::COMMAND_NAME => purge::handle(matches, settings)?,
+            serve::COMMAND_NAME => serve::handle(matches, settings, logging_guard.log_level())?,
             &_ => {}
         }
     }
@@ -0,0 +1,112 @@
+use crate::services::audit::AuditContext;
+use crate::services::post::{MySQLPostService, PostService};
+use crate::settings::Settings;
+use chrono::{Duration, Utc};
+use clap::{Arg, ArgMatches, Command};
+
+pub const COMMAND_NAME: &str = "purge";
+
+pub fn configure() -> Command {
+    Command::new(COMMAND_NAME)
+        .about("Remove deleted posts from the trash for good")
+        .arg(
+            Arg::new("older-than")
+                .long("older-than")
+                .value_name("AGE")
+                .help("Only posts deleted longer ago than this, e.g. 30d, 12h or 90m")
+                .default_value("30d")
+                .value_parser(parse_age),
+        )
+}
+
+pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
+    tokio::runtime::Handle::current().block_on(async move {
+        let db_url = settings
+            .database
+            .url
+            .clone()
+            .expect("Database URL is not set");
+        let pool = sqlx::MySqlPool::connect(&db_url).await?;
+        let service = MySQLPostService::new(pool, &settings.database, &settings.revisions);
+
+        let older_than = matches
+            .get_one::<Duration>("older-than")
+            .cloned()
+            .unwrap_or(Duration::days(30));
+        let audit = AuditContext {
+            actor: Some(COMMAND_NAME.to_string()),
+            ..Default::default()
+        };
+
+        let deleted_before = Utc::now()
+            .checked_sub_signed(older_than)
+            .ok_or_else(|| anyhow::anyhow!("Age is too large: {}", older_than))?;
+
+        let posts = service.purge_posts(deleted_before, &audit).await?;
+        for post in &posts {
+            tracing::info!(post_id = post.id, slug = post.slug.as_str(), "Post purged");
+        }
+        println!("Purged {} posts", posts.len());
+
+        Ok(())
+    })
+}
+
+/// Parses a number followed by `d`, `h`, `m` or `s`. Ages that reach back
+/// further than dates can are rejected, instead of overflowing later.
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let unit_at = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or("Missing unit, expected d, h, m or s")?;
+    let (amount, unit) = value.split_at(unit_at);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|e| format!("Invalid amount: {}", e))?;
+
+    let age = match unit {
+        "d" => Duration::try_days(amount),
+        "h" => Duration::try_hours(amount),
+        "m" => Duration::try_minutes(amount),
+        "s" => Duration::try_seconds(amount),
+        _ => return Err(format!("Unknown unit {}, expected d, h, m or s", unit)),
+    };
+
+    age.filter(|age| Utc::now().checked_sub_signed(*age).is_some())
+        .ok_or_else(|| format!("Age is too large: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_age;
+    use chrono::Duration;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_age("30d"), Ok(Duration::days(30)));
+        assert_eq!(parse_age("12h"), Ok(Duration::hours(12)));
+        assert_eq!(parse_age("90m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_age("45s"), Ok(Duration::seconds(45)));
+    }
+
+    #[test]
+    fn rejects_missing_or_unknown_units() {
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("30w").is_err());
+        assert!(parse_age("30dd").is_err());
+        assert!(parse_age("").is_err());
+    }
+
+    #[test]
+    fn rejects_missing_or_negative_amounts() {
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("-1d").is_err());
+    }
+
+    #[test]
+    fn rejects_ages_that_overflow() {
+        assert!(parse_age("9223372036854775807d").is_err());
+        assert!(parse_age("99999999999999999999s").is_err());
+        assert!(parse_age("9223372036854775807s").is_err());
+        assert!(parse_age("100000000d").is_err());
+    }
+}
@@ -1,120 +1,208 @@
+#[cfg(feature = "http3")]
+use crate::api::middleware::limits;
+use crate::logging::LogLevelHandle;
+use crate::scheduler;
+use crate::server::tls::Certificates;
+use crate::server::{self, BindAddress, ConnectionOptions, Listener};
 use crate::settings::Settings;
 use crate::state::ApplicationState;
-use clap::{value_parser, Arg, ArgMatches, Command};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use anyhow::Context;
+use axum::http::{header, HeaderValue};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::Layer;
-
-use crate::settings::OtlpTarget;
-use opentelemetry::trace::{TraceError, TracerProvider};
-use opentelemetry::{global, KeyValue};
-use opentelemetry_otlp::{WithExportConfig, WithHttpConfig};
-use opentelemetry_sdk::propagation::TraceContextPropagator;
-use opentelemetry_sdk::trace::{RandomIdGenerator, Sampler, Tracer};
-use opentelemetry_sdk::{runtime, trace, Resource};
-use std::collections::HashMap;
+use tokio::task::JoinSet;
+use tower_http::set_header::SetResponseHeaderLayer;
 
 pub const COMMAND_NAME: &str = "serve";
 
-pub fn init_tracer(otlp_target: &OtlpTarget) -> Result<Tracer, TraceError> {
-    global::set_text_map_propagator(TraceContextPropagator::new());
-
-    let otlp_endpoint = otlp_target.address.as_str();
-
-    let mut builder = opentelemetry_otlp::SpanExporter::builder()
-        .with_http()
-        .with_endpoint(otlp_endpoint);
-
-    if let Some(authorization) = &otlp_target.authorization {
-        let mut headers = HashMap::new();
-        headers.insert(String::from("Authorization"), authorization.clone());
-        builder = builder.with_headers(headers);
-    };
-
-    let exporter = builder.build()?;
-
-    let tracer_provider = trace::TracerProvider::builder()
-        .with_batch_exporter(exporter, runtime::Tokio)
-        .with_config(
-            trace::Config::default()
-                .with_sampler(Sampler::AlwaysOn)
-                .with_id_generator(RandomIdGenerator::default())
-                .with_max_events_per_span(64)
-                .with_max_attributes_per_span(16)
-                .with_max_events_per_span(16)
-                .with_resource(Resource::new(vec![KeyValue::new(
-                    "service.name",
-                    "sample_application",
-                )])),
+pub fn configure() -> Command {
+    Command::new(COMMAND_NAME)
+        .about("Start HTTP server")
+        .arg(
+            Arg::new("port")
+                .short('p')
+                .long("port")
+                .value_name("PORT")
+                .help("TCP port to listen on [default: 8080]")
+                .value_parser(value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("bind")
+                .short('b')
+                .long("bind")
+                .value_name("ADDRESS")
+                .help("Address to listen on, e.g. 0.0.0.0, ::, [::1]:9000 or unix:/run/app.sock")
+                .action(ArgAction::Append),
         )
-        .build();
-
-    Ok(tracer_provider.tracer("sample_application"))
 }
 
-pub fn configure() -> Command {
-    Command::new(COMMAND_NAME).about("Start HTTP server").arg(
-        Arg::new("port")
-            .short('p')
-            .long("port")
-            .value_name("PORT")
-            .help("TCP port to listen on")
-            .default_value("8080")
-            .value_parser(value_parser!(u16)),
-    )
-}
+pub fn handle(
+    matches: &ArgMatches,
+    settings: &Settings,
+    log_level: LogLevelHandle,
+) -> anyhow::Result<()> {
+    let port = matches
+        .get_one::<u16>("port")
+        .copied()
+        .or(settings.server.port)
+        .unwrap_or(8080);
+
+    let bind: Vec<String> = match matches.get_many::<String>("bind") {
+        Some(values) => values.cloned().collect(),
+        None => settings.server.bind.clone(),
+    };
+    let bind = if bind.is_empty() {
+        vec!["0.0.0.0".to_string()]
+    } else {
+        bind
+    };
 
-pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
-    let port: u16 = *matches.get_one("port").unwrap_or(&8080);
+    let addresses = bind
+        .iter()
+        .map(|value| BindAddress::parse(value, port))
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-    start_tokio(port, settings)?;
+    start_tokio(port, &addresses, settings, log_level)?;
 
     Ok(())
 }
 
-fn start_tokio(port: u16, settings: &Settings) -> anyhow::Result<()> {
-    tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()?
-        .block_on(async move {
-            let telemetry_layer = if let Some(otlp_target) = settings.logging.otlp_target.clone() {
-                let tracer = init_tracer(&otlp_target)?;
-                Some(tracing_opentelemetry::layer().with_tracer(tracer))
-            } else {
-                None
+fn start_tokio(
+    port: u16,
+    addresses: &[BindAddress],
+    settings: &Settings,
+    log_level: LogLevelHandle,
+) -> anyhow::Result<()> {
+    let unix_socket_mode = settings
+        .server
+        .unix_socket_mode
+        .as_deref()
+        .map(|mode| u32::from_str_radix(mode, 8))
+        .transpose()
+        .context("Invalid server.unix_socket_mode")?;
+
+    tokio::runtime::Handle::current().block_on(async move {
+        let db_url = settings
+            .database
+            .url
+            .clone()
+            .expect("Database URL is not set");
+        let pool = sqlx::MySqlPool::connect(&db_url).await?;
+
+        let state = Arc::new(ApplicationState::new(settings, pool, log_level)?);
+
+        #[cfg(unix)]
+        tokio::spawn(reload_settings_on_hangup(state.clone()));
+        tokio::spawn(scheduler::run(state.clone()));
+
+        let router = crate::api::configure(state);
+
+        let certificates = match &settings.server.tls {
+            Some(tls) => Some(Certificates::load(tls)?),
+            None => None,
+        };
+        let acceptor = certificates
+            .as_ref()
+            .map(|certificates| certificates.acceptor(settings.server.http2.unwrap_or(true)))
+            .transpose()?;
+
+        let options = ConnectionOptions::new(&settings.server);
+        let mut servers = JoinSet::new();
+
+        if let Some(redirect_port) = settings
+            .server
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.redirect_http_port)
+        {
+            let redirect_addresses: Vec<_> = addresses
+                .iter()
+                .filter_map(|address| match address {
+                    BindAddress::Tcp(addr) => {
+                        Some(BindAddress::Tcp(SocketAddr::new(addr.ip(), redirect_port)))
+                    }
+                    #[cfg(unix)]
+                    BindAddress::Unix(_) => None,
+                })
+                .collect();
+
+            for listener in Listener::bind_all(&redirect_addresses, None)? {
+                servers.spawn(server::serve(
+                    listener,
+                    server::redirect_to_https(port),
+                    None,
+                    options.clone(),
+                ));
+            }
+        }
+
+        let http3 = settings.server.http3;
+        #[cfg(feature = "http3")]
+        if http3 {
+            let certificates = certificates
+                .clone()
+                .context("server.http3 requires server.tls")?;
+            let body_limit = limits::largest_body_limit(&settings.server);
+
+            for address in addresses {
+                if let BindAddress::Tcp(addr) = address {
+                    servers.spawn(server::http3::serve(
+                        *addr,
+                        router.clone(),
+                        certificates.clone(),
+                        body_limit,
+                        options.clone(),
+                    ));
+                }
+            }
+        }
+        #[cfg(not(feature = "http3"))]
+        if http3 {
+            tracing::warn!("server.http3 is ignored, HTTP/3 support was not compiled in");
+        }
+
+        let listeners = Listener::bind_all(addresses, unix_socket_mode)?;
+        for (address, listener) in addresses.iter().zip(listeners) {
+            let router = match address {
+                BindAddress::Tcp(addr) if http3 && cfg!(feature = "http3") => {
+                    router.clone().layer(SetResponseHeaderLayer::if_not_present(
+                        header::ALT_SVC,
+                        HeaderValue::from_str(&format!("h3=\":{}\"; ma=86400", addr.port()))?,
+                    ))
+                }
+                _ => router.clone(),
             };
 
-            let stdout_log = tracing_subscriber::fmt::layer().with_filter(
-                tracing_subscriber::EnvFilter::try_from_default_env()
-                    .unwrap_or(tracing_subscriber::EnvFilter::new("info")),
-            );
+            servers.spawn(server::serve(
+                listener,
+                router,
+                acceptor.clone(),
+                options.clone(),
+            ));
+        }
 
-            let subscriber = tracing_subscriber::registry()
-                .with(telemetry_layer)
-                .with(stdout_log);
+        while let Some(result) = servers.join_next().await {
+            result??;
+        }
 
-            subscriber.init();
-
-            let db_url = settings
-                .database
-                .url
-                .clone()
-                .expect("Database URL is not set");
-            let pool = sqlx::MySqlPool::connect(&db_url).await?;
+        Ok::<(), anyhow::Error>(())
+    })?;
 
-            let state = Arc::new(ApplicationState::new(settings, pool)?);
-            let router = crate::api::configure(state).layer(TraceLayer::new_for_http());
-
-            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
-
-            let listener = tokio::net::TcpListener::bind(addr).await?;
-            axum::serve(listener, router.into_make_service()).await?;
+    Ok(())
+}
 
-            Ok::<(), anyhow::Error>(())
-        })?;
+/// Re-reads the settings whenever the process receives SIGHUP.
+#[cfg(unix)]
+async fn reload_settings_on_hangup(state: Arc<ApplicationState>) -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        if let Err(e) = state.reload_settings() {
+            tracing::error!("Failed to reload settings: {:?}", e);
+        }
+    }
 
     Ok(())
 }
@@ -1,6 +1,9 @@
 pub mod api;
 pub mod commands;
+pub mod logging;
 pub mod model;
+pub mod scheduler;
+pub mod server;
 pub mod services;
 pub mod settings;
 pub mod state;
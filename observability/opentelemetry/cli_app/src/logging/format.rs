@@ -0,0 +1,161 @@
+use opentelemetry::trace::{SpanId, TraceContextExt, TraceId};
+use serde::ser::{SerializeMap, Serializer};
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_opentelemetry::OtelData;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+/// Writes events as JSON lines with the `trace_id` and `span_id` of the
+/// current span, so log records can be correlated with the exported traces.
+/// Otherwise the lines look like those of `tracing_subscriber`'s JSON format.
+pub struct JsonWithTraceIds;
+
+impl<S, N> FormatEvent<S, N> for JsonWithTraceIds
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = JsonVisitor::default();
+        event.record(&mut fields);
+
+        let spans: Vec<Value> = ctx
+            .event_scope()
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| span_value::<S, N>(&span))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let record = Record {
+            level: metadata.level().as_str(),
+            ids: trace_ids(ctx),
+            fields: &fields.0,
+            target: metadata.target(),
+            spans: &spans,
+        };
+        let mut line = Vec::new();
+        record
+            .serialize(&mut serde_json::Serializer::new(&mut line))
+            .map_err(|_| fmt::Error)?;
+
+        writer.write_str(std::str::from_utf8(&line).map_err(|_| fmt::Error)?)?;
+        writeln!(writer)
+    }
+}
+
+struct Record<'a> {
+    level: &'a str,
+    ids: Option<(TraceId, SpanId)>,
+    fields: &'a Map<String, Value>,
+    target: &'a str,
+    spans: &'a [Value],
+}
+
+impl Record<'_> {
+    fn serialize<W: std::io::Write>(
+        &self,
+        serializer: &mut serde_json::Serializer<W>,
+    ) -> Result<(), serde_json::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry(
+            "timestamp",
+            &chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        )?;
+        map.serialize_entry("level", self.level)?;
+        if let Some((trace_id, span_id)) = self.ids {
+            map.serialize_entry("trace_id", &trace_id.to_string())?;
+            map.serialize_entry("span_id", &span_id.to_string())?;
+        }
+        map.serialize_entry("fields", self.fields)?;
+        map.serialize_entry("target", self.target)?;
+        if let Some(span) = self.spans.last() {
+            map.serialize_entry("span", span)?;
+            map.serialize_entry("spans", self.spans)?;
+        }
+        map.end()
+    }
+}
+
+fn trace_ids<S, N>(ctx: &FmtContext<'_, S, N>) -> Option<(TraceId, SpanId)>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let span = ctx.lookup_current()?;
+    let extensions = span.extensions();
+    let data = extensions.get::<OtelData>()?;
+    let trace_id = data
+        .builder
+        .trace_id
+        .unwrap_or_else(|| data.parent_cx.span().span_context().trace_id());
+    let span_id = data.builder.span_id?;
+
+    (trace_id != TraceId::INVALID).then_some((trace_id, span_id))
+}
+
+/// The name and fields of a span. The fields were already formatted as a
+/// JSON object by the `JsonFields` of the layer.
+fn span_value<S, N>(span: &SpanRef<'_, S>) -> Value
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let mut value = span
+        .extensions()
+        .get::<FormattedFields<N>>()
+        .and_then(|fields| serde_json::from_str::<Map<String, Value>>(fields).ok())
+        .unwrap_or_default();
+    value.insert("name".to_string(), Value::from(span.name()));
+
+    Value::Object(value)
+}
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0
+            .insert(field.name().to_string(), Value::from(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            Value::from(format!("{:?}", value)),
+        );
+    }
+}
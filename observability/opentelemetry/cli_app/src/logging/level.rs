@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+use utoipa::ToSchema;
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct LogLevel {
+    pub level: String,
+    pub default_level: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+struct LogLevelState {
+    current: LogLevel,
+    /// Set through the admin API, with or without a TTL. Reloading the
+    /// settings does not replace it.
+    overridden: bool,
+    generation: u64,
+}
+
+/// Changes the log filter of the running subscriber. Temporary overrides
+/// fall back to the default level once their TTL is over.
+#[derive(Clone)]
+pub struct LogLevelHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    state: Arc<Mutex<LogLevelState>>,
+}
+
+impl LogLevelHandle {
+    pub(super) fn new(handle: reload::Handle<EnvFilter, Registry>, level: String) -> Self {
+        Self {
+            handle,
+            state: Arc::new(Mutex::new(LogLevelState {
+                current: LogLevel {
+                    level: level.clone(),
+                    default_level: level,
+                    expires_at: None,
+                },
+                overridden: false,
+                generation: 0,
+            })),
+        }
+    }
+
+    pub fn get(&self) -> LogLevel {
+        self.lock().current.clone()
+    }
+
+    pub fn set(&self, level: &str, ttl: Option<Duration>) -> anyhow::Result<LogLevel> {
+        let mut state = self.lock();
+        self.handle.reload(EnvFilter::try_new(level)?)?;
+
+        state.generation += 1;
+        state.overridden = true;
+        state.current.level = level.to_string();
+        state.current.expires_at = ttl.and_then(|ttl| {
+            chrono::Duration::from_std(ttl)
+                .ok()
+                .map(|ttl| Utc::now() + ttl)
+        });
+
+        if let Some(ttl) = ttl {
+            let handle = self.clone();
+            let generation = state.generation;
+            tokio::spawn(async move {
+                tokio::time::sleep(ttl).await;
+                if let Err(e) = handle.revert(generation) {
+                    tracing::error!("Failed to revert log level: {:?}", e);
+                }
+            });
+        }
+
+        Ok(state.current.clone())
+    }
+
+    /// Replaces the default level, e.g. after the settings were reloaded.
+    /// An override stays in place, a temporary one until it expires.
+    pub fn set_default(&self, level: &str) -> anyhow::Result<()> {
+        EnvFilter::try_new(level)?;
+
+        let mut state = self.lock();
+        state.current.default_level = level.to_string();
+
+        if !state.overridden {
+            self.handle.reload(EnvFilter::try_new(level)?)?;
+            state.current.level = level.to_string();
+        }
+
+        Ok(())
+    }
+
+    fn revert(&self, generation: u64) -> anyhow::Result<()> {
+        let mut state = self.lock();
+        if state.generation != generation {
+            return Ok(());
+        }
+
+        let default_level = state.current.default_level.clone();
+        self.handle.reload(EnvFilter::try_new(&default_level)?)?;
+        state.current.level = default_level;
+        state.current.expires_at = None;
+        state.overridden = false;
+
+        tracing::info!("Log level reverted to {}", state.current.level);
+
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LogLevelState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
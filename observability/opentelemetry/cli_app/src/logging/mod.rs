@@ -0,0 +1,126 @@
+mod format;
+mod level;
+pub mod otlp;
+mod request_debug;
+
+pub use level::{LogLevel, LogLevelHandle};
+pub use request_debug::{debug_request, DEBUG_LOG_FIELD};
+
+use crate::settings::{LogFile, LogFormat, LogRotation, Logging, Settings};
+use format::JsonWithTraceIds;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::trace;
+use request_debug::RequestDebugFilter;
+use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer};
+
+/// Keeps the log file writer and the tracer provider alive. Dropping it
+/// flushes buffered log lines and pending spans.
+pub struct LoggingGuard {
+    _file_guard: Option<WorkerGuard>,
+    tracer_provider: Option<trace::TracerProvider>,
+    log_level: LogLevelHandle,
+}
+
+impl LoggingGuard {
+    pub fn log_level(&self) -> LogLevelHandle {
+        self.log_level.clone()
+    }
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if let Some(tracer_provider) = self.tracer_provider.take() {
+            let _ = tracer_provider.shutdown();
+        }
+    }
+}
+
+pub fn init(settings: &Settings) -> anyhow::Result<LoggingGuard> {
+    let logging = &settings.logging;
+
+    let mut outputs = vec![output_layer(logging.format, std::io::stdout, true)];
+
+    let file_guard = match &logging.file {
+        Some(file) => {
+            let (writer, guard) = tracing_appender::non_blocking(file_appender(file)?);
+            outputs.push(output_layer(logging.format, writer, false));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    let tracer_provider = match &logging.otlp_target {
+        Some(otlp_target) => Some(otlp::init_tracer_provider(otlp_target)?),
+        None => None,
+    };
+
+    let telemetry_layer = tracer_provider.as_ref().map(|tracer_provider| {
+        tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("sample_application"))
+    });
+
+    let level = default_level(logging);
+    let (filter, handle) = reload::Layer::new(EnvFilter::try_new(&level)?);
+
+    tracing_subscriber::registry()
+        .with(outputs.with_filter(RequestDebugFilter::new(filter, logging.allow_debug_header)))
+        .with(telemetry_layer)
+        .try_init()?;
+
+    Ok(LoggingGuard {
+        _file_guard: file_guard,
+        tracer_provider,
+        log_level: LogLevelHandle::new(handle, level),
+    })
+}
+
+/// `RUST_LOG` takes precedence over `logging.level`, which accepts the full
+/// `EnvFilter` directive syntax, e.g. `info,cli_app=debug,sqlx=warn`.
+pub fn default_level(logging: &Logging) -> String {
+    std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|level| !level.is_empty())
+        .or(logging.level.clone())
+        .unwrap_or("info".to_string())
+}
+
+fn output_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+
+    match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Json => layer.json().event_format(JsonWithTraceIds).boxed(),
+    }
+}
+
+fn file_appender(file: &LogFile) -> anyhow::Result<BasicRollingFileAppender> {
+    let mut condition = RollingConditionBasic::new();
+
+    match file.rotation {
+        LogRotation::Never => {}
+        LogRotation::Daily => condition = condition.daily(),
+        LogRotation::Size => {
+            condition = condition.max_size(file.max_size_mb.unwrap_or(100) * 1024 * 1024)
+        }
+    }
+
+    Ok(BasicRollingFileAppender::new(
+        &file.path,
+        condition,
+        file.max_files.unwrap_or(7),
+    )?)
+}
@@ -0,0 +1,200 @@
+use super::request_debug::RequestDebugSampler;
+use crate::settings::{OtlpCompression, OtlpProtocol, OtlpTarget, SamplerKind};
+use opentelemetry::trace::TraceError;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{
+    Compression, Protocol, SpanExporter, WithExportConfig, WithHttpConfig, WithTonicConfig,
+};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::resource::{
+    EnvResourceDetector, ResourceDetector, SdkProvidedResourceDetector, TelemetryResourceDetector,
+};
+use opentelemetry_sdk::trace::{RandomIdGenerator, Sampler};
+use opentelemetry_sdk::{runtime, trace, Resource};
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use tonic::metadata::MetadataMap;
+use tonic::transport::{Certificate, ClientTlsConfig};
+
+pub fn init_tracer_provider(otlp_target: &OtlpTarget) -> Result<trace::TracerProvider, TraceError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let exporter = build_exporter(otlp_target)?;
+
+    let limits = &otlp_target.span_limits;
+
+    // `trace::Config::default()` already reads the standard OTEL_* variables,
+    // so the settings are only applied where the environment is silent.
+    let mut config = trace::Config::default()
+        .with_id_generator(RandomIdGenerator::default())
+        .with_resource(build_resource(otlp_target));
+
+    if env::var("OTEL_TRACES_SAMPLER").is_err() {
+        config = config.with_sampler(build_sampler(otlp_target));
+    }
+    if env::var("OTEL_SPAN_EVENT_COUNT_LIMIT").is_err() {
+        config = config.with_max_events_per_span(limits.max_events_per_span.unwrap_or(64));
+    }
+    if env::var("OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT").is_err() {
+        config = config.with_max_attributes_per_span(limits.max_attributes_per_span.unwrap_or(16));
+    }
+    if env::var("OTEL_SPAN_LINK_COUNT_LIMIT").is_err() {
+        if let Some(max) = limits.max_links_per_span {
+            config = config.with_max_links_per_span(max);
+        }
+    }
+    if let Some(max) = limits.max_attributes_per_event {
+        config = config.with_max_attributes_per_event(max);
+    }
+    if let Some(max) = limits.max_attributes_per_link {
+        config = config.with_max_attributes_per_link(max);
+    }
+
+    // Requests with a trusted X-Debug-Log header are sampled in any case.
+    config.sampler = Box::new(RequestDebugSampler(config.sampler));
+
+    let tracer_provider = trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_config(config)
+        .build();
+
+    Ok(tracer_provider)
+}
+
+fn build_exporter(otlp_target: &OtlpTarget) -> Result<SpanExporter, TraceError> {
+    let otlp_endpoint = otlp_target.address.as_str();
+    let timeout = Duration::from_secs(otlp_target.timeout_seconds.unwrap_or(10));
+
+    let ca_certificate = match &otlp_target.tls_ca_path {
+        Some(path) => Some(std::fs::read(path).map_err(|e| {
+            TraceError::from(format!(
+                "Failed to read OTLP CA certificate {}: {}",
+                path, e
+            ))
+        })?),
+        None => None,
+    };
+
+    if otlp_target.protocol == OtlpProtocol::Grpc {
+        let mut builder = SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(otlp_endpoint)
+            .with_timeout(timeout);
+
+        if let Some(authorization) = &otlp_target.authorization {
+            let mut metadata = MetadataMap::new();
+            let value = authorization
+                .parse()
+                .map_err(|_| TraceError::from("Invalid OTLP authorization value"))?;
+            metadata.insert("authorization", value);
+            builder = builder.with_metadata(metadata);
+        }
+
+        if let Some(compression) = otlp_target.compression {
+            builder = builder.with_compression(match compression {
+                OtlpCompression::Gzip => Compression::Gzip,
+                OtlpCompression::Zstd => Compression::Zstd,
+            });
+        }
+
+        if let Some(ca_certificate) = ca_certificate {
+            builder = builder.with_tls_config(
+                ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca_certificate)),
+            );
+        } else if otlp_endpoint.starts_with("https://") {
+            builder = builder.with_tls_config(ClientTlsConfig::new().with_native_roots());
+        }
+
+        return builder.build();
+    }
+
+    if otlp_target.compression.is_some() {
+        return Err(TraceError::from(
+            "OTLP compression is only supported with the grpc protocol",
+        ));
+    }
+
+    let protocol = match otlp_target.protocol {
+        OtlpProtocol::HttpProtobuf => Protocol::HttpBinary,
+        _ => Protocol::HttpJson,
+    };
+
+    let mut builder = SpanExporter::builder()
+        .with_http()
+        .with_protocol(protocol)
+        .with_endpoint(otlp_endpoint)
+        .with_timeout(timeout);
+
+    if let Some(authorization) = &otlp_target.authorization {
+        let mut headers = HashMap::new();
+        headers.insert(String::from("Authorization"), authorization.clone());
+        builder = builder.with_headers(headers);
+    };
+
+    if let Some(ca_certificate) = ca_certificate {
+        let certificate = reqwest::Certificate::from_pem(&ca_certificate)
+            .map_err(|e| TraceError::from(format!("Invalid OTLP CA certificate: {}", e)))?;
+        let client = reqwest::Client::builder()
+            .add_root_certificate(certificate)
+            .timeout(timeout)
+            .build()
+            .map_err(|e| TraceError::from(format!("Failed to build OTLP client: {}", e)))?;
+        builder = builder.with_http_client(client);
+    }
+
+    builder.build()
+}
+
+fn build_sampler(otlp_target: &OtlpTarget) -> Sampler {
+    let ratio = otlp_target.sampler_ratio.unwrap_or(1.0);
+
+    match otlp_target.sampler {
+        SamplerKind::Always => Sampler::AlwaysOn,
+        SamplerKind::Never => Sampler::AlwaysOff,
+        SamplerKind::Ratio => Sampler::TraceIdRatioBased(ratio),
+        SamplerKind::ParentRatio => {
+            Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio)))
+        }
+    }
+}
+
+fn build_resource(otlp_target: &OtlpTarget) -> Resource {
+    let service_name = otlp_target
+        .service_name
+        .clone()
+        .unwrap_or("sample_application".to_string());
+
+    let mut attributes = vec![
+        KeyValue::new("service.name", service_name),
+        KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        KeyValue::new(
+            "host.name",
+            gethostname::gethostname().to_string_lossy().to_string(),
+        ),
+        KeyValue::new("host.arch", env::consts::ARCH),
+        KeyValue::new("os.type", env::consts::OS),
+    ];
+
+    if let Some(environment) = &otlp_target.environment {
+        attributes.push(KeyValue::new("deployment.environment", environment.clone()));
+    }
+
+    attributes.extend(
+        otlp_target
+            .resource_attributes
+            .iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+    );
+
+    // OTEL_RESOURCE_ATTRIBUTES and OTEL_SERVICE_NAME win over the settings.
+    let mut detectors: Vec<Box<dyn ResourceDetector>> = vec![
+        Box::new(TelemetryResourceDetector),
+        Box::new(EnvResourceDetector::new()),
+    ];
+    if env::var("OTEL_SERVICE_NAME").is_ok() {
+        detectors.push(Box::new(SdkProvidedResourceDetector));
+    }
+
+    Resource::new(attributes).merge(&Resource::from_detectors(Duration::from_secs(0), detectors))
+}
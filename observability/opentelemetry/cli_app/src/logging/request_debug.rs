@@ -0,0 +1,178 @@
+use opentelemetry::trace::{
+    Link, SamplingDecision, SamplingResult, SpanKind, TraceContextExt, TraceId, TraceState,
+};
+use opentelemetry::{Context as OtelContext, KeyValue};
+use opentelemetry_sdk::trace::ShouldSample;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::level_filters::LevelFilter;
+use tracing::subscriber::Interest;
+use tracing::{span, Event, Metadata};
+use tracing_subscriber::layer::{Context, Filter};
+
+/// Span field set on `http_request` when it is created for a request that
+/// asked for debug logs, so the sampler sees it when the trace starts.
+pub const DEBUG_LOG_FIELD: &str = "debug_log";
+
+const TRACE_STATE_KEY: &str = "debug";
+
+/// Number of debugged requests being handled. Callsite interest is only
+/// widened while there is at least one.
+static DEBUG_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+tokio::task_local! {
+    static REQUEST_DEBUG_LEVEL: LevelFilter;
+}
+
+/// Runs `future` with log output raised to `level`.
+pub async fn debug_request<F: Future>(level: LevelFilter, future: F) -> F::Output {
+    let _in_flight = InFlight::start();
+
+    REQUEST_DEBUG_LEVEL.scope(level, future).await
+}
+
+fn request_level() -> Option<LevelFilter> {
+    REQUEST_DEBUG_LEVEL.try_with(|level| *level).ok()
+}
+
+fn debug_in_flight() -> bool {
+    DEBUG_REQUESTS.load(Ordering::SeqCst) > 0
+}
+
+/// Counts a debugged request until it is dropped. The first and the last one
+/// make every callsite re-evaluate its interest.
+struct InFlight;
+
+impl InFlight {
+    fn start() -> Self {
+        if DEBUG_REQUESTS.fetch_add(1, Ordering::SeqCst) == 0 {
+            tracing::callsite::rebuild_interest_cache();
+        }
+        InFlight
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if DEBUG_REQUESTS.fetch_sub(1, Ordering::SeqCst) == 1 {
+            tracing::callsite::rebuild_interest_cache();
+        }
+    }
+}
+
+/// Lets spans and events through that the wrapped filter rejects, as long as
+/// they are emitted while a debugged request is being handled.
+pub struct RequestDebugFilter<F> {
+    inner: F,
+    enabled: bool,
+}
+
+impl<F> RequestDebugFilter<F> {
+    pub fn new(inner: F, enabled: bool) -> Self {
+        Self { inner, enabled }
+    }
+
+    fn debug_enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.enabled && request_level().is_some_and(|level| *metadata.level() <= level)
+    }
+}
+
+impl<S, F: Filter<S>> Filter<S> for RequestDebugFilter<F> {
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        self.inner.enabled(meta, cx) || self.debug_enabled(meta)
+    }
+
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> Interest {
+        let interest = self.inner.callsite_enabled(meta);
+
+        // While a request is debugged, callsites must be re-evaluated on every
+        // call, otherwise a disabled callsite would stay disabled for it too.
+        if self.enabled && debug_in_flight() && interest.is_never() {
+            Interest::sometimes()
+        } else {
+            interest
+        }
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, cx: &Context<'_, S>) -> bool {
+        self.inner.event_enabled(event, cx) || self.debug_enabled(event.metadata())
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        if self.enabled && debug_in_flight() {
+            Some(LevelFilter::TRACE)
+        } else {
+            self.inner.max_level_hint()
+        }
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_new_span(attrs, id, ctx)
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        self.inner.on_record(id, values, ctx)
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_enter(id, ctx)
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.inner.on_exit(id, ctx)
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        self.inner.on_close(id, ctx)
+    }
+}
+
+/// Samples every span of a debugged request, regardless of the configured
+/// sampler. The decision is passed on to child spans through the trace state.
+#[derive(Clone, Debug)]
+pub struct RequestDebugSampler(pub Box<dyn ShouldSample>);
+
+impl ShouldSample for RequestDebugSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&OtelContext>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        let parent_state = parent_context
+            .filter(|cx| cx.has_active_span())
+            .map(|cx| cx.span().span_context().trace_state().clone());
+
+        let forced = request_level().is_some()
+            || attributes
+                .iter()
+                .any(|kv| kv.key.as_str() == DEBUG_LOG_FIELD)
+            || parent_state
+                .as_ref()
+                .is_some_and(|state| state.get(TRACE_STATE_KEY).is_some());
+
+        if !forced {
+            return self.0.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            );
+        }
+
+        let trace_state = parent_state.unwrap_or_else(TraceState::default);
+
+        SamplingResult {
+            decision: SamplingDecision::RecordAndSample,
+            attributes: Vec::new(),
+            trace_state: trace_state
+                .insert(TRACE_STATE_KEY, "1")
+                .unwrap_or_else(|_| trace_state.clone()),
+        }
+    }
+}
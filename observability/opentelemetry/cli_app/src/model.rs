@@ -31,29 +31,126 @@ impl From<UserStatus> for i32 {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum UserRole {
+    Author = 1,
+    Editor = 2,
+    Admin = 3,
+}
+
+impl TryFrom<i32> for UserRole {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(UserRole::Author),
+            2 => Ok(UserRole::Editor),
+            3 => Ok(UserRole::Admin),
+            _ => Err(anyhow!("Unknown user role: {}", value)),
+        }
+    }
+}
+
+impl From<UserRole> for i32 {
+    fn from(value: UserRole) -> Self {
+        match value {
+            UserRole::Author => 1,
+            UserRole::Editor => 2,
+            UserRole::Admin => 3,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, ToSchema)]
 pub struct User {
     pub id: i64,
     pub username: String,
     pub password: String,
     pub status: UserStatus,
+    pub role: UserRole,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum PostStatus {
     Draft = 1,
     Published = 2,
+    InReview = 3,
+    ChangesRequested = 4,
+    Archived = 5,
+    /// Approved, published automatically at `publish_at`.
+    Scheduled = 6,
 }
 
-impl From<i32> for PostStatus {
-    fn from(value: i32) -> Self {
+/// Transitions of the editorial workflow and the role needed for them.
+/// `UserRole::Author` stands for the author of the post; editors and
+/// administrators may make every transition.
+const POST_TRANSITIONS: [(PostStatus, PostStatus, UserRole); 9] = [
+    (PostStatus::Draft, PostStatus::InReview, UserRole::Author),
+    (
+        PostStatus::ChangesRequested,
+        PostStatus::InReview,
+        UserRole::Author,
+    ),
+    (
+        PostStatus::InReview,
+        PostStatus::Published,
+        UserRole::Editor,
+    ),
+    (
+        PostStatus::InReview,
+        PostStatus::ChangesRequested,
+        UserRole::Editor,
+    ),
+    (
+        PostStatus::InReview,
+        PostStatus::Scheduled,
+        UserRole::Editor,
+    ),
+    (
+        PostStatus::Scheduled,
+        PostStatus::Published,
+        UserRole::Editor,
+    ),
+    // Takes a post off the schedule for another review.
+    (
+        PostStatus::Scheduled,
+        PostStatus::InReview,
+        UserRole::Editor,
+    ),
+    (
+        PostStatus::Published,
+        PostStatus::Archived,
+        UserRole::Editor,
+    ),
+    (PostStatus::Archived, PostStatus::Draft, UserRole::Editor),
+];
+
+impl PostStatus {
+    /// The role needed to move a post from `self` to `to`, or `None` if the
+    /// workflow does not allow it.
+    pub fn transition(self, to: PostStatus) -> Option<UserRole> {
+        POST_TRANSITIONS
+            .iter()
+            .find(|(from, target, _)| *from == self && *target == to)
+            .map(|(_, _, role)| *role)
+    }
+}
+
+impl TryFrom<i32> for PostStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
         match value {
-            1 => PostStatus::Draft,
-            2 => PostStatus::Published,
-            _ => PostStatus::Draft,
+            1 => Ok(PostStatus::Draft),
+            2 => Ok(PostStatus::Published),
+            3 => Ok(PostStatus::InReview),
+            4 => Ok(PostStatus::ChangesRequested),
+            5 => Ok(PostStatus::Archived),
+            6 => Ok(PostStatus::Scheduled),
+            _ => Err(anyhow!("Unknown post status: {}", value)),
         }
     }
 }
@@ -63,11 +160,54 @@ impl From<PostStatus> for i32 {
         match value {
             PostStatus::Draft => 1,
             PostStatus::Published => 2,
+            PostStatus::InReview => 3,
+            PostStatus::ChangesRequested => 4,
+            PostStatus::Archived => 5,
+            PostStatus::Scheduled => 6,
         }
     }
 }
 
-#[derive(Clone, Serialize, ToSchema)]
+/// Who can find and read a published post.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum PostVisibility {
+    /// Listed and readable by everyone.
+    #[default]
+    Public = 1,
+    /// Readable by everyone with the link, but not listed.
+    Unlisted = 2,
+    /// Listed and readable for logged-in members only.
+    Private = 3,
+    /// Readable with the post's password, but not listed.
+    Password = 4,
+}
+
+impl TryFrom<i32> for PostVisibility {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(PostVisibility::Public),
+            2 => Ok(PostVisibility::Unlisted),
+            3 => Ok(PostVisibility::Private),
+            4 => Ok(PostVisibility::Password),
+            _ => Err(anyhow!("Unknown post visibility: {}", value)),
+        }
+    }
+}
+
+impl From<PostVisibility> for i32 {
+    fn from(value: PostVisibility) -> Self {
+        match value {
+            PostVisibility::Public => 1,
+            PostVisibility::Unlisted => 2,
+            PostVisibility::Private => 3,
+            PostVisibility::Password => 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
 pub struct Post {
     pub id: i64,
     pub author_id: i64,
@@ -75,8 +215,52 @@ pub struct Post {
     pub title: String,
     pub content: String,
     pub status: PostStatus,
+    /// Why the post was sent back to its author, set when changes are
+    /// requested.
+    pub review_comment: Option<String>,
+    /// When a scheduled post goes live.
+    pub publish_at: Option<DateTime<Utc>>,
+    /// When a published post is archived again.
+    pub unpublish_at: Option<DateTime<Utc>>,
+    pub visibility: PostVisibility,
+    /// Argon2 hash of the password of password-protected posts.
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    pub version: i64,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
+    /// When the post was moved to the trash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// The title and content of a post as they were after an update. `revision`
+/// is the version of the post the update created.
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct PostRevision {
+    pub post_id: i64,
+    pub revision: i64,
+    pub author: Option<String>,
+    pub message: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub actor: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    #[schema(value_type = Object)]
+    pub changes: serde_json::Value,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub created: DateTime<Utc>,
+    pub prev_hash: String,
+    pub hash: String,
 }
 
 pub fn validate_password(password: &str, hash: &str) -> anyhow::Result<()> {
@@ -0,0 +1,39 @@
+use crate::services::audit::AuditContext;
+use crate::services::post::PostService;
+use crate::state::ApplicationState;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Publishes and unpublishes posts when they are due. Every replica runs the
+/// scheduler; posts are locked while they are transitioned, so each one is
+/// handled by a single replica. The settings are read on every run, so the
+/// interval can be changed by reloading them.
+pub async fn run(state: Arc<ApplicationState>) {
+    let audit = AuditContext {
+        actor: Some("scheduler".to_string()),
+        ..Default::default()
+    };
+
+    loop {
+        let settings = state.settings.load().scheduler.clone();
+        tokio::time::sleep(Duration::from_secs(settings.interval_seconds.unwrap_or(30))).await;
+
+        if !settings.enabled.unwrap_or(true) {
+            continue;
+        }
+
+        match state.post_service.run_schedule(&audit).await {
+            Ok(posts) => {
+                for post in posts {
+                    tracing::info!(
+                        post_id = post.id,
+                        slug = post.slug.as_str(),
+                        status = ?post.status,
+                        "Scheduled post transitioned"
+                    );
+                }
+            }
+            Err(e) => tracing::error!("Failed to run post schedule: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::tls::{Certificates, ClientCertificate};
+use super::{acquire_connection, Activity, ConnectionOptions};
+use crate::api::errors::AppError;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use axum::response::IntoResponse;
+use axum::Router;
+use bytes::{Buf, Bytes};
+use h3::server::RequestStream;
+use http_body_util::BodyExt;
+use quinn::crypto::rustls::QuicServerConfig;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio_rustls::rustls::version::TLS13;
+use tower::ServiceExt;
+
+/// Serves `router` over HTTP/3 on the UDP port `addr` until the process
+/// exits. Request bodies larger than `body_limit` are rejected with 413.
+/// Connections count towards the same limit as those of the TCP listeners
+/// and are closed after the same idle timeout.
+pub async fn serve(
+    addr: SocketAddr,
+    router: Router,
+    certificates: Certificates,
+    body_limit: usize,
+    options: ConnectionOptions,
+) -> anyhow::Result<()> {
+    let tls = certificates.server_config(&[&TLS13], vec![b"h3".to_vec()])?;
+    let config = quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls)?));
+    let endpoint = quinn::Endpoint::server(config, addr)?;
+
+    tracing::info!("Listening on {} (HTTP/3)", addr);
+
+    loop {
+        let permit = match &options.connections {
+            Some(connections) => Some(acquire_connection(connections).await?),
+            None => None,
+        };
+
+        let Some(incoming) = endpoint.accept().await else {
+            break;
+        };
+        let router = router.clone();
+        let options = options.clone();
+
+        tokio::spawn(async move {
+            let _permit = permit;
+
+            let connection =
+                match tokio::time::timeout(options.header_read_timeout, incoming.into_future())
+                    .await
+                {
+                    Ok(Ok(connection)) => connection,
+                    Ok(Err(e)) => {
+                        tracing::debug!("QUIC handshake failed: {:?}", e);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!("QUIC handshake timed out");
+                        return;
+                    }
+                };
+            let remote_addr = connection.remote_address();
+            let client_cert = ClientCertificate::from_quic(&connection);
+
+            let result = serve_connection(
+                connection,
+                remote_addr,
+                client_cert,
+                router,
+                body_limit,
+                &options,
+            )
+            .await;
+            if let Err(e) = result {
+                tracing::debug!("Connection to {} closed: {:?}", remote_addr, e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn serve_connection(
+    connection: quinn::Connection,
+    remote_addr: SocketAddr,
+    client_cert: Option<ClientCertificate>,
+    router: Router,
+    body_limit: usize,
+    options: &ConnectionOptions,
+) -> anyhow::Result<()> {
+    let mut connection = h3::server::Connection::new(h3_quinn::Connection::new(connection)).await?;
+    let activity = Arc::new(Activity::new());
+
+    loop {
+        tokio::select! {
+            accepted = connection.accept() => {
+                let Some((req, stream)) = accepted? else {
+                    return Ok(());
+                };
+                let router = router.clone();
+                let client_cert = client_cert.clone();
+
+                let in_flight = activity.start();
+                tokio::spawn(async move {
+                    let result =
+                        handle_request(req, stream, remote_addr, client_cert, router, body_limit)
+                            .await;
+                    drop(in_flight);
+                    if let Err(e) = result {
+                        tracing::debug!("Failed to handle HTTP/3 request: {:?}", e);
+                    }
+                });
+            }
+            _ = tokio::time::sleep(activity.idle_remaining(options.idle_timeout)) => {
+                if activity.idle_remaining(options.idle_timeout).is_zero() {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Sends GOAWAY, no request is in flight at this point.
+    connection.shutdown(0).await?;
+
+    Ok(())
+}
+
+async fn handle_request<S>(
+    req: Request<()>,
+    mut stream: RequestStream<S, Bytes>,
+    remote_addr: SocketAddr,
+    client_cert: Option<ClientCertificate>,
+    router: Router,
+    body_limit: usize,
+) -> anyhow::Result<()>
+where
+    S: h3::quic::BidiStream<Bytes>,
+{
+    let mut body = Vec::new();
+    while let Some(mut chunk) = stream.recv_data().await? {
+        if body.len() + chunk.remaining() > body_limit {
+            let response = AppError::from((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                anyhow::anyhow!("Request body is larger than {} bytes", body_limit),
+            ))
+            .into_response();
+            return send_response(&mut stream, response).await;
+        }
+
+        while chunk.has_remaining() {
+            let bytes = chunk.chunk();
+            body.extend_from_slice(bytes);
+            let len = bytes.len();
+            chunk.advance(len);
+        }
+    }
+
+    let (parts, ()) = req.into_parts();
+    let mut req = Request::from_parts(parts, Body::from(body));
+    req.extensions_mut().insert(ConnectInfo(remote_addr));
+    if let Some(client_cert) = client_cert {
+        req.extensions_mut().insert(client_cert);
+    }
+
+    let response = router.oneshot(req).await?;
+
+    send_response(&mut stream, response).await
+}
+
+async fn send_response<S>(
+    stream: &mut RequestStream<S, Bytes>,
+    response: axum::response::Response,
+) -> anyhow::Result<()>
+where
+    S: h3::quic::BidiStream<Bytes>,
+{
+    let (parts, mut body) = response.into_parts();
+    stream
+        .send_response(axum::http::Response::from_parts(parts, ()))
+        .await?;
+
+    while let Some(frame) = body.frame().await {
+        if let Ok(data) = frame?.into_data() {
+            stream.send_data(data).await?;
+        }
+    }
+
+    stream.finish().await?;
+
+    Ok(())
+}
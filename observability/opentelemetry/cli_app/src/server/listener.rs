@@ -0,0 +1,225 @@
+use anyhow::Context;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+
+/// One entry of `server.bind`: an IP address with an optional port, or a Unix
+/// socket path prefixed with `unix:`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+impl BindAddress {
+    pub fn parse(value: &str, default_port: u16) -> anyhow::Result<Self> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            if path.is_empty() {
+                anyhow::bail!("Missing Unix socket path: {}", value);
+            }
+            #[cfg(unix)]
+            return Ok(BindAddress::Unix(path.into()));
+            #[cfg(not(unix))]
+            anyhow::bail!("Unix sockets are not supported on this platform: {}", path);
+        }
+
+        if let Ok(addr) = value.parse::<SocketAddr>() {
+            return Ok(BindAddress::Tcp(addr));
+        }
+
+        // IPv6 addresses without a port may be written in brackets, too.
+        value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .unwrap_or(value)
+            .parse::<IpAddr>()
+            .map(|ip| BindAddress::Tcp(SocketAddr::new(ip, default_port)))
+            .with_context(|| format!("Invalid bind address: {}", value))
+    }
+}
+
+impl fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddress::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            BindAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+impl Listener {
+    /// Binds all addresses. IPv6 wildcard addresses accept IPv4 connections
+    /// as well, unless an IPv4 address with the same port is bound, too.
+    /// `unix_socket_mode` sets the permissions of Unix socket files.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub fn bind_all(
+        addresses: &[BindAddress],
+        unix_socket_mode: Option<u32>,
+    ) -> anyhow::Result<Vec<Listener>> {
+        let mut listeners = Vec::with_capacity(addresses.len());
+
+        for address in addresses {
+            let listener = match address {
+                BindAddress::Tcp(addr) => {
+                    let only_v6 = addresses.iter().any(|other| {
+                        matches!(other, BindAddress::Tcp(other) if other.is_ipv4() && other.port() == addr.port())
+                    });
+                    Listener::Tcp(bind_tcp(*addr, only_v6)?)
+                }
+                #[cfg(unix)]
+                BindAddress::Unix(path) => Listener::Unix(bind_unix(path, unix_socket_mode)?),
+            };
+
+            tracing::info!("Listening on {}", address);
+            listeners.push(listener);
+        }
+
+        Ok(listeners)
+    }
+}
+
+fn bind_tcp(addr: SocketAddr, only_v6: bool) -> anyhow::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+    if addr.is_ipv6() {
+        socket.set_only_v6(only_v6)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&addr.into())
+        .with_context(|| format!("Failed to bind {}", addr))?;
+    socket.listen(1024)?;
+
+    Ok(TcpListener::from_std(socket.into())?)
+}
+
+#[cfg(unix)]
+fn bind_unix(
+    path: &std::path::Path,
+    mode: Option<u32>,
+) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    // A socket left behind by a previous run would make bind fail. Anything
+    // that is not a socket is left alone.
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind {}", path.display()))?;
+
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+
+    Ok(listener)
+}
+
+/// Connections accepted by a `Listener`, together with the peer address if
+/// there is one.
+pub(super) trait Accept {
+    type Io: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    async fn accept(&self) -> std::io::Result<(Self::Io, Option<SocketAddr>)>;
+}
+
+impl Accept for TcpListener {
+    type Io = tokio::net::TcpStream;
+
+    async fn accept(&self) -> std::io::Result<(Self::Io, Option<SocketAddr>)> {
+        let (stream, addr) = TcpListener::accept(self).await?;
+        Ok((stream, Some(addr)))
+    }
+}
+
+#[cfg(unix)]
+impl Accept for tokio::net::UnixListener {
+    type Io = tokio::net::UnixStream;
+
+    async fn accept(&self) -> std::io::Result<(Self::Io, Option<SocketAddr>)> {
+        let (stream, _) = tokio::net::UnixListener::accept(self).await?;
+        Ok((stream, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BindAddress;
+    use std::net::SocketAddr;
+
+    fn tcp(addr: &str) -> BindAddress {
+        BindAddress::Tcp(addr.parse::<SocketAddr>().unwrap())
+    }
+
+    #[test]
+    fn uses_the_default_port_without_one() {
+        assert_eq!(
+            BindAddress::parse("0.0.0.0", 8080).unwrap(),
+            tcp("0.0.0.0:8080")
+        );
+        assert_eq!(BindAddress::parse("::", 8080).unwrap(), tcp("[::]:8080"));
+        assert_eq!(
+            BindAddress::parse("[::1]", 8080).unwrap(),
+            tcp("[::1]:8080")
+        );
+    }
+
+    #[test]
+    fn keeps_an_explicit_port() {
+        assert_eq!(
+            BindAddress::parse("127.0.0.1:9000", 8080).unwrap(),
+            tcp("127.0.0.1:9000")
+        );
+        assert_eq!(
+            BindAddress::parse("[::1]:9000", 8080).unwrap(),
+            tcp("[::1]:9000")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parses_unix_sockets() {
+        assert_eq!(
+            BindAddress::parse("unix:/run/app.sock", 8080).unwrap(),
+            BindAddress::Unix("/run/app.sock".into())
+        );
+        assert!(BindAddress::parse("unix:", 8080).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        for value in [
+            "",
+            "localhost",
+            "127.0.0.1:99999",
+            "[::1",
+            "::1]",
+            "[[::1]]",
+            "::1:9000:",
+        ] {
+            assert!(BindAddress::parse(value, 8080).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        assert_eq!(
+            BindAddress::parse("[::1]:9000", 8080).unwrap().to_string(),
+            "[::1]:9000"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+#[cfg(feature = "http3")]
+pub mod http3;
+mod listener;
+pub mod tls;
+
+pub use listener::{BindAddress, Listener};
+
+use crate::api::errors::AppError;
+use crate::settings::Server;
+use anyhow::anyhow;
+use axum::extract::ConnectInfo;
+use axum::http::{header, uri::Authority, HeaderMap, Request, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use listener::Accept;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+/// Connection level limits, shared by all listeners of the server.
+#[derive(Clone)]
+pub struct ConnectionOptions {
+    header_read_timeout: Duration,
+    idle_timeout: Duration,
+    keep_alive: bool,
+    http2: bool,
+    connections: Option<Arc<Semaphore>>,
+}
+
+impl ConnectionOptions {
+    pub fn new(server: &Server) -> Self {
+        Self {
+            header_read_timeout: Duration::from_secs(
+                server.header_read_timeout_seconds.unwrap_or(10),
+            ),
+            idle_timeout: Duration::from_secs(server.idle_timeout_seconds.unwrap_or(60)),
+            keep_alive: server.keep_alive.unwrap_or(true),
+            http2: server.http2.unwrap_or(true),
+            connections: server
+                .max_connections
+                .map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+}
+
+/// Accepts connections on `listener` until the process exits. With an
+/// acceptor, TLS is terminated before requests are passed to `router`.
+pub async fn serve(
+    listener: Listener,
+    router: Router,
+    tls: Option<TlsAcceptor>,
+    options: ConnectionOptions,
+) -> anyhow::Result<()> {
+    match listener {
+        Listener::Tcp(listener) => accept_loop(listener, router, tls, options).await,
+        #[cfg(unix)]
+        Listener::Unix(listener) => accept_loop(listener, router, tls, options).await,
+    }
+}
+
+async fn accept_loop<L: Accept>(
+    listener: L,
+    router: Router,
+    tls: Option<TlsAcceptor>,
+    options: ConnectionOptions,
+) -> anyhow::Result<()> {
+    loop {
+        // At the connection limit no more connections are accepted; new ones
+        // wait in the listen backlog until an open one is closed.
+        let permit = match &options.connections {
+            Some(connections) => Some(acquire_connection(connections).await?),
+            None => None,
+        };
+
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Usually running out of file descriptors, which only gets
+                // better once other connections are closed.
+                tracing::error!("Failed to accept connection: {:?}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let router = router.clone();
+        let options = options.clone();
+        let tls = tls.clone();
+
+        tokio::spawn(async move {
+            let _permit = permit;
+
+            match tls {
+                Some(tls) => {
+                    let handshake =
+                        tokio::time::timeout(options.header_read_timeout, tls.accept(stream));
+                    match handshake.await {
+                        Ok(Ok(stream)) => {
+                            let client_cert = tls::ClientCertificate::from_connection(&stream);
+                            serve_connection(stream, remote_addr, client_cert, router, &options)
+                                .await
+                        }
+                        Ok(Err(e)) => {
+                            tracing::debug!("TLS handshake with {:?} failed: {:?}", remote_addr, e)
+                        }
+                        Err(_) => tracing::debug!("TLS handshake with {:?} timed out", remote_addr),
+                    }
+                }
+                None => serve_connection(stream, remote_addr, None, router, &options).await,
+            }
+        });
+    }
+}
+
+async fn serve_connection<I>(
+    io: I,
+    remote_addr: Option<SocketAddr>,
+    client_cert: Option<tls::ClientCertificate>,
+    router: Router,
+    options: &ConnectionOptions,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let activity = Arc::new(Activity::new());
+
+    let service = {
+        let activity = activity.clone();
+        hyper::service::service_fn(move |mut req: Request<Incoming>| {
+            if let Some(remote_addr) = remote_addr {
+                req.extensions_mut().insert(ConnectInfo(remote_addr));
+            }
+            if let Some(client_cert) = &client_cert {
+                req.extensions_mut().insert(client_cert.clone());
+            }
+
+            let response = router.clone().oneshot(req);
+            let in_flight = activity.start();
+            async move {
+                let response = response.await;
+                drop(in_flight);
+                response
+            }
+        })
+    };
+
+    // Without TLS, HTTP/2 is detected by its connection preface (h2c with
+    // prior knowledge); with TLS, it is negotiated through ALPN.
+    let mut builder = Builder::new(TokioExecutor::new());
+    if !options.http2 {
+        builder = builder.http1_only();
+    }
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(options.header_read_timeout)
+        .keep_alive(options.keep_alive);
+    builder.http2().timer(TokioTimer::new());
+
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+    tokio::pin!(connection);
+
+    let mut closing = false;
+    let result = loop {
+        tokio::select! {
+            result = connection.as_mut() => break result,
+            _ = tokio::time::sleep(activity.idle_remaining(options.idle_timeout)), if !closing => {
+                if activity.idle_remaining(options.idle_timeout).is_zero() {
+                    connection.as_mut().graceful_shutdown();
+                    closing = true;
+                }
+            }
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::debug!("Connection to {:?} closed: {:?}", remote_addr, e);
+    }
+}
+
+/// Tracks the requests of a connection to close it once it has been idle
+/// for longer than the idle timeout.
+struct Activity {
+    in_flight: AtomicUsize,
+    last_active: Mutex<Instant>,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Counts a request as in flight until the returned guard is dropped,
+    /// also when the request is cancelled, e.g. by a reset HTTP/2 stream.
+    fn start(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(self.clone())
+    }
+
+    fn finish(&self) {
+        *self.last_active.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn idle_remaining(&self, idle_timeout: Duration) -> Duration {
+        if self.in_flight.load(Ordering::SeqCst) > 0 {
+            return idle_timeout;
+        }
+
+        let last_active = *self.last_active.lock().unwrap_or_else(|e| e.into_inner());
+        idle_timeout.saturating_sub(last_active.elapsed())
+    }
+}
+
+struct InFlight(Arc<Activity>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// Waits for a free connection slot, warning once whenever the limit is hit.
+pub(crate) async fn acquire_connection(
+    connections: &Arc<Semaphore>,
+) -> anyhow::Result<OwnedSemaphorePermit> {
+    if let Ok(permit) = connections.clone().try_acquire_owned() {
+        return Ok(permit);
+    }
+
+    tracing::warn!("Connection limit reached, waiting for connections to close");
+
+    Ok(connections.clone().acquire_owned().await?)
+}
+
+/// Answers every plain HTTP request with a permanent redirect to the same
+/// URL on the HTTPS port.
+pub fn redirect_to_https(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        redirect(https_port, &headers, &uri)
+    })
+}
+
+fn redirect(https_port: u16, headers: &HeaderMap, uri: &Uri) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok());
+
+    let Some(host) = host else {
+        return AppError::from((StatusCode::BAD_REQUEST, anyhow!("Missing Host header")))
+            .into_response();
+    };
+
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    let location = match https_port {
+        443 => format!("https://{}{}", host.host(), path),
+        port => format!("https://{}:{}{}", host.host(), port, path),
+    };
+
+    Redirect::permanent(&location).into_response()
+}
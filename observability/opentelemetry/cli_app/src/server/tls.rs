@@ -0,0 +1,213 @@
+use crate::settings::{ClientPrincipal, Tls};
+use anyhow::anyhow;
+use arc_swap::ArcSwap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::{
+    RootCertStore, ServerConfig, SupportedProtocolVersion, DEFAULT_VERSIONS,
+};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// Hands out the certificate that was most recently loaded from disk, so
+/// renewed certificates are picked up without restarting the server.
+#[derive(Debug)]
+struct ReloadingCertResolver {
+    key: ArcSwap<CertifiedKey>,
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.load_full())
+    }
+}
+
+/// The server certificate from `server.tls`, shared by all listeners and
+/// reloaded when the files change.
+#[derive(Clone)]
+pub struct Certificates {
+    tls: Tls,
+    resolver: Arc<ReloadingCertResolver>,
+}
+
+impl Certificates {
+    pub fn load(tls: &Tls) -> anyhow::Result<Self> {
+        let resolver = Arc::new(ReloadingCertResolver {
+            key: ArcSwap::from_pointee(load_certified_key(tls)?),
+        });
+
+        tokio::spawn(reload_on_change(tls.clone(), resolver.clone()));
+
+        Ok(Self {
+            tls: tls.clone(),
+            resolver,
+        })
+    }
+
+    pub fn acceptor(&self, http2: bool) -> anyhow::Result<TlsAcceptor> {
+        let alpn_protocols = if http2 {
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        } else {
+            vec![b"http/1.1".to_vec()]
+        };
+
+        let config = self.server_config(DEFAULT_VERSIONS, alpn_protocols)?;
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    pub fn server_config(
+        &self,
+        versions: &[&'static SupportedProtocolVersion],
+        alpn_protocols: Vec<Vec<u8>>,
+    ) -> anyhow::Result<ServerConfig> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(versions)?;
+
+        let builder = if self.tls.client_ca_paths.is_empty() {
+            builder.with_no_client_auth()
+        } else {
+            // Certificates are optional on the TLS level; whether a route
+            // requires one is decided by the `auth` middleware.
+            let verifier = WebPkiClientVerifier::builder_with_provider(
+                Arc::new(client_roots(&self.tls)?),
+                provider,
+            )
+            .allow_unauthenticated()
+            .build()?;
+            builder.with_client_cert_verifier(verifier)
+        };
+
+        let mut config = builder.with_cert_resolver(self.resolver.clone());
+        config.alpn_protocols = alpn_protocols;
+
+        Ok(config)
+    }
+}
+
+fn client_roots(tls: &Tls) -> anyhow::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+
+    for path in &tls.client_ca_paths {
+        for cert in rustls_pemfile::certs(&mut BufReader::new(File::open(path)?)) {
+            roots.add(cert?)?;
+        }
+    }
+
+    Ok(roots)
+}
+
+/// A client certificate that was verified against the configured CAs during
+/// the TLS handshake. Available to handlers as an `Extension`.
+#[derive(Clone, Debug)]
+pub struct ClientCertificate {
+    pub subject: String,
+    pub sans: Vec<String>,
+}
+
+impl ClientCertificate {
+    pub fn from_connection<IO>(stream: &TlsStream<IO>) -> Option<Self> {
+        Self::from_der(stream.get_ref().1.peer_certificates()?.first()?)
+    }
+
+    /// The end-entity certificate a QUIC client authenticated with.
+    #[cfg(feature = "http3")]
+    pub fn from_quic(connection: &quinn::Connection) -> Option<Self> {
+        use tokio_rustls::rustls::pki_types::CertificateDer;
+
+        let identity = connection.peer_identity()?;
+        let certs = identity.downcast_ref::<Vec<CertificateDer<'static>>>()?;
+        Self::from_der(certs.first()?)
+    }
+
+    fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+        let sans = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_string()),
+                    GeneralName::RFC822Name(name) => Some(name.to_string()),
+                    GeneralName::URI(uri) => Some(uri.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Some(ClientCertificate {
+            subject: cert.subject().to_string(),
+            sans,
+        })
+    }
+
+    /// The first configured principal this certificate matches.
+    pub fn principal<'a>(&self, tls: &'a Tls) -> Option<&'a ClientPrincipal> {
+        tls.client_principals.iter().find(|principal| {
+            principal.subject.as_ref() == Some(&self.subject)
+                || principal
+                    .san
+                    .as_ref()
+                    .is_some_and(|san| self.sans.contains(san))
+        })
+    }
+}
+
+fn load_certified_key(tls: &Tls) -> anyhow::Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&tls.cert_path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificate found in {}", tls.cert_path);
+    }
+
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&tls.key_path)?))?
+        .ok_or_else(|| anyhow!("No private key found in {}", tls.key_path))?;
+
+    Ok(CertifiedKey::new(
+        certs,
+        ring::sign::any_supported_type(&key)?,
+    ))
+}
+
+/// Polls the modification times of the certificate and key, which also works
+/// for files that are replaced through symlinks, e.g. Kubernetes secrets.
+async fn reload_on_change(tls: Tls, resolver: Arc<ReloadingCertResolver>) {
+    let interval = Duration::from_secs(tls.reload_interval_seconds.unwrap_or(30));
+    let mut loaded = modified(&tls);
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let current = modified(&tls);
+        if current == loaded {
+            continue;
+        }
+
+        match load_certified_key(&tls) {
+            Ok(key) => {
+                resolver.key.store(Arc::new(key));
+                loaded = current;
+                tracing::info!("TLS certificate reloaded from {}", tls.cert_path);
+            }
+            Err(e) => tracing::error!("Failed to reload TLS certificate: {:?}", e),
+        }
+    }
+}
+
+fn modified(tls: &Tls) -> Option<(SystemTime, SystemTime)> {
+    let cert = std::fs::metadata(&tls.cert_path).ok()?.modified().ok()?;
+    let key = std::fs::metadata(&tls.key_path).ok()?.modified().ok()?;
+
+    Some((cert, key))
+}
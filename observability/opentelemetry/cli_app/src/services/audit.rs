@@ -0,0 +1,505 @@
+use crate::model::AuditEvent;
+use crate::services::query::QueryObserver;
+use crate::settings::Database;
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Execute, MySql, MySqlPool, Transaction};
+use tracing::instrument;
+use utoipa::IntoParams;
+
+/// Who triggered a change, captured from the request that caused it.
+#[derive(Clone, Debug, Default)]
+pub struct AuditContext {
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+}
+
+pub struct NewAuditEvent {
+    pub action: &'static str,
+    pub target_type: &'static str,
+    pub target_id: Option<String>,
+    pub changes: Value,
+}
+
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AuditService {
+    async fn record(&self, context: &AuditContext, event: NewAuditEvent) -> anyhow::Result<()>;
+    async fn list_events(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEvent>>;
+}
+
+/// Returns the fields that differ between the two serialized values as
+/// `{"field": {"before": .., "after": ..}}`. Values of `redacted` fields are
+/// never written, only the fact that they changed.
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>, redacted: &[&str]) -> Value {
+    let to_map = |value: Option<&T>| match value.map(serde_json::to_value) {
+        Some(Ok(Value::Object(map))) => map,
+        _ => Map::new(),
+    };
+    let before = to_map(before);
+    let after = to_map(after);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old == new || changes.contains_key(key) {
+            continue;
+        }
+
+        let change = if redacted.contains(&key.as_str()) {
+            json!({ "before": "[redacted]", "after": "[redacted]" })
+        } else {
+            json!({ "before": old, "after": new })
+        };
+        changes.insert(key.clone(), change);
+    }
+
+    Value::Object(changes)
+}
+
+/// Appends an event to the audit trail as part of `tx`, so that it is only
+/// stored if the audited change is committed. Every event carries the hash of
+/// its predecessor, which makes later modifications of the trail detectable.
+///
+/// The hash of the last event is kept in the single row of
+/// `audit_chain_head`. Locking that row by its primary key orders concurrent
+/// writers without locking any range of `audit_events`.
+pub async fn record_event(
+    tx: &mut Transaction<'_, MySql>,
+    observer: &QueryObserver,
+    context: &AuditContext,
+    event: NewAuditEvent,
+) -> anyhow::Result<()> {
+    let prev_hash = match lock_chain_head(tx, observer).await? {
+        Some(hash) => hash,
+        None => {
+            init_chain_head(tx, observer).await?;
+            lock_chain_head(tx, observer)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Audit chain head is missing"))?
+        }
+    };
+
+    let changes = event.changes.to_string();
+    let created = Utc::now().trunc_subsecs(0);
+    let hash = chain_hash(
+        &prev_hash,
+        context.actor.as_deref(),
+        event.action,
+        event.target_type,
+        event.target_id.as_deref(),
+        &changes,
+        context.ip.as_deref(),
+        context.request_id.as_deref(),
+        &created,
+    );
+
+    let query = sqlx::query!(
+        r#"
+            INSERT INTO audit_events
+                (actor, action, target_type, target_id, changes, ip, request_id, created, prev_hash, hash)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        context.actor,
+        event.action,
+        event.target_type,
+        event.target_id,
+        changes,
+        context.ip,
+        context.request_id,
+        created,
+        prev_hash,
+        hash
+    );
+
+    observer
+        .observe(
+            "INSERT",
+            "audit_events",
+            query.sql(),
+            |r| r.rows_affected(),
+            query.execute(&mut **tx),
+        )
+        .await?;
+
+    let query = sqlx::query!(
+        r#"
+            UPDATE audit_chain_head
+            SET hash = ?
+            WHERE id = 1
+        "#,
+        hash
+    );
+
+    observer
+        .observe(
+            "UPDATE",
+            "audit_chain_head",
+            query.sql(),
+            |r| r.rows_affected(),
+            query.execute(&mut **tx),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn lock_chain_head(
+    tx: &mut Transaction<'_, MySql>,
+    observer: &QueryObserver,
+) -> anyhow::Result<Option<String>> {
+    let head = sqlx::query!(
+        r#"
+            SELECT hash
+            FROM audit_chain_head
+            WHERE id = 1
+            FOR UPDATE
+        "#
+    );
+
+    Ok(observer
+        .observe(
+            "SELECT",
+            "audit_chain_head",
+            head.sql(),
+            |row| row.is_some() as u64,
+            head.fetch_optional(&mut **tx),
+        )
+        .await?
+        .map(|row| row.hash))
+}
+
+/// Creates the chain head of databases that do not have one yet, pointing at
+/// the last event already stored.
+async fn init_chain_head(
+    tx: &mut Transaction<'_, MySql>,
+    observer: &QueryObserver,
+) -> anyhow::Result<()> {
+    let query = sqlx::query!(
+        r#"
+            INSERT IGNORE INTO audit_chain_head (id, hash)
+            SELECT 1, COALESCE(
+                (SELECT hash FROM audit_events ORDER BY id DESC LIMIT 1),
+                ''
+            )
+        "#
+    );
+
+    observer
+        .observe(
+            "INSERT",
+            "audit_chain_head",
+            query.sql(),
+            |r| r.rows_affected(),
+            query.execute(&mut **tx),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn chain_hash(
+    prev_hash: &str,
+    actor: Option<&str>,
+    action: &str,
+    target_type: &str,
+    target_id: Option<&str>,
+    changes: &str,
+    ip: Option<&str>,
+    request_id: Option<&str>,
+    created: &DateTime<Utc>,
+) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        prev_hash,
+        actor.unwrap_or_default(),
+        action,
+        target_type,
+        target_id.unwrap_or_default(),
+        changes,
+        ip.unwrap_or_default(),
+        request_id.unwrap_or_default(),
+        &created.timestamp().to_string(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Recomputes the hash chain one event at a time, so the trail can be
+/// checked page by page in the order of the event IDs.
+pub struct ChainVerifier {
+    /// The hash in `audit_chain_head` when the check started.
+    head: String,
+    prev_hash: String,
+    events: u64,
+    reached_head: bool,
+}
+
+impl ChainVerifier {
+    /// Events recorded after `head` was read continue the chain past it, so
+    /// the chain only has to pass through the head, not end there.
+    pub fn new(head: String) -> Self {
+        Self {
+            reached_head: head.is_empty(),
+            head,
+            prev_hash: String::new(),
+            events: 0,
+        }
+    }
+
+    /// Checks the next event, returning its ID if it does not continue the
+    /// chain.
+    pub fn check(&mut self, event: &AuditEvent) -> Result<(), i64> {
+        let hash = chain_hash(
+            &self.prev_hash,
+            event.actor.as_deref(),
+            &event.action,
+            &event.target_type,
+            event.target_id.as_deref(),
+            &event.changes.to_string(),
+            event.ip.as_deref(),
+            event.request_id.as_deref(),
+            &event.created,
+        );
+
+        if event.prev_hash != self.prev_hash || event.hash != hash {
+            return Err(event.id);
+        }
+
+        self.reached_head |= hash == self.head;
+        self.prev_hash = hash;
+        self.events += 1;
+
+        Ok(())
+    }
+
+    /// Returns the number of checked events, or an error if the chain never
+    /// reached the head, i.e. the newest events were removed.
+    pub fn finish(self) -> anyhow::Result<u64> {
+        match self.reached_head {
+            true => Ok(self.events),
+            false => anyhow::bail!(
+                "Audit log does not reach the chain head {}, the newest events were removed",
+                self.head
+            ),
+        }
+    }
+}
+
+pub struct MySQLAuditService {
+    pub pool: MySqlPool,
+    observer: QueryObserver,
+}
+
+impl MySQLAuditService {
+    pub fn new(pool: MySqlPool, settings: &Database) -> Self {
+        Self {
+            pool,
+            observer: QueryObserver::new(settings),
+        }
+    }
+}
+
+impl MySQLAuditService {
+    /// The hash of the last recorded event, empty if there is none yet.
+    #[instrument(skip(self))]
+    pub async fn chain_head(&self) -> anyhow::Result<String> {
+        let head = sqlx::query!(
+            r#"
+                SELECT hash
+                FROM audit_chain_head
+                WHERE id = 1
+            "#
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "audit_chain_head",
+                head.sql(),
+                |row| row.is_some() as u64,
+                head.fetch_optional(&self.pool),
+            )
+            .await?
+            .map(|row| row.hash)
+            .ok_or_else(|| anyhow::anyhow!("Audit chain head is missing"))
+    }
+}
+
+impl AuditService for MySQLAuditService {
+    #[instrument(skip(self, context, event), fields(action = event.action))]
+    async fn record(&self, context: &AuditContext, event: NewAuditEvent) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        record_event(&mut tx, &self.observer, context, event).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, filter))]
+    async fn list_events(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEvent>> {
+        let limit = filter.limit.unwrap_or(100).min(1000);
+        let offset = filter.offset.unwrap_or(0);
+
+        let res = sqlx::query!(
+            r#"
+                SELECT id, actor, action, target_type, target_id, changes, ip, request_id,
+                    created, prev_hash, hash
+                FROM audit_events
+                WHERE (? IS NULL OR actor = ?)
+                    AND (? IS NULL OR action = ?)
+                    AND (? IS NULL OR target_type = ?)
+                    AND (? IS NULL OR target_id = ?)
+                    AND (? IS NULL OR created >= ?)
+                    AND (? IS NULL OR created < ?)
+                ORDER BY id
+                LIMIT ? OFFSET ?
+            "#,
+            filter.actor,
+            filter.actor,
+            filter.action,
+            filter.action,
+            filter.target_type,
+            filter.target_type,
+            filter.target_id,
+            filter.target_id,
+            filter.since,
+            filter.since,
+            filter.until,
+            filter.until,
+            limit,
+            offset
+        );
+
+        let rows = self
+            .observer
+            .observe(
+                "SELECT",
+                "audit_events",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&self.pool),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(e).context("Failed to list audit events"))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AuditEvent {
+                id: row.id,
+                actor: row.actor,
+                action: row.action,
+                target_type: row.target_type,
+                target_id: row.target_id,
+                changes: serde_json::from_str(&row.changes).unwrap_or(Value::Null),
+                ip: row.ip,
+                request_id: row.request_id,
+                created: row.created,
+                prev_hash: row.prev_hash,
+                hash: row.hash,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chain_hash, ChainVerifier};
+    use crate::model::AuditEvent;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn chain(len: i64) -> Vec<AuditEvent> {
+        let mut prev_hash = String::new();
+        (1..=len)
+            .map(|id| {
+                let created = Utc.timestamp_opt(1_700_000_000 + id, 0).unwrap();
+                let changes = json!({ "title": { "before": null, "after": id } });
+                let hash = chain_hash(
+                    &prev_hash,
+                    Some("alice"),
+                    "post.update",
+                    "post",
+                    Some("1"),
+                    &changes.to_string(),
+                    None,
+                    None,
+                    &created,
+                );
+                AuditEvent {
+                    id,
+                    actor: Some("alice".to_string()),
+                    action: "post.update".to_string(),
+                    target_type: "post".to_string(),
+                    target_id: Some("1".to_string()),
+                    changes,
+                    ip: None,
+                    request_id: None,
+                    created,
+                    prev_hash: std::mem::replace(&mut prev_hash, hash.clone()),
+                    hash,
+                }
+            })
+            .collect()
+    }
+
+    fn verify(events: &[AuditEvent], head: &str) -> anyhow::Result<u64> {
+        let mut verifier = ChainVerifier::new(head.to_string());
+        for event in events {
+            verifier
+                .check(event)
+                .map_err(|id| anyhow::anyhow!("modified at {}", id))?;
+        }
+        verifier.finish()
+    }
+
+    #[test]
+    fn accepts_intact_chain() {
+        let events = chain(3);
+        assert_eq!(verify(&events, &events[2].hash).unwrap(), 3);
+        assert_eq!(verify(&[], "").unwrap(), 0);
+    }
+
+    #[test]
+    fn accepts_events_recorded_after_the_head_was_read() {
+        let events = chain(3);
+        assert_eq!(verify(&events, &events[1].hash).unwrap(), 3);
+    }
+
+    #[test]
+    fn detects_modified_events() {
+        let mut events = chain(3);
+        events[1].actor = Some("mallory".to_string());
+        let head = events[2].hash.clone();
+        assert!(verify(&events, &head).is_err());
+    }
+
+    #[test]
+    fn detects_removed_events() {
+        let events = chain(3);
+        let head = events[2].hash.clone();
+        assert!(verify(&events[..2], &head).is_err());
+        assert!(verify(&[], &head).is_err());
+        assert!(verify(&[events[0].clone(), events[2].clone()], &head).is_err());
+    }
+}
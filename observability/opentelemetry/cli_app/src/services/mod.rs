@@ -1,2 +1,4 @@
+pub mod audit;
 pub mod post;
+pub mod query;
 pub mod user;
@@ -1,27 +1,172 @@
-use crate::model::{Post, PostStatus};
+use crate::model::{encrypt_password, Post, PostRevision, PostStatus, PostVisibility};
+use crate::services::audit::{self, AuditContext, NewAuditEvent};
+use crate::services::query::QueryObserver;
+use crate::settings::{Database, Revisions};
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use sqlx::MySqlPool;
-use std::collections::HashMap;
+use sqlx::{Execute, Executor, MySql, MySqlPool, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use tokio::sync::Mutex;
+use tracing::instrument;
 use utoipa::ToSchema;
 
 #[allow(async_fn_in_trait)]
 pub trait PostService {
-    async fn get_all_posts(&self) -> anyhow::Result<Vec<Post>>;
+    async fn get_all_posts(
+        &self,
+        reader: Reader,
+        status: Option<PostStatus>,
+    ) -> anyhow::Result<Vec<Post>>;
     async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post>;
     async fn get_post_by_slug(&self, name: &str) -> anyhow::Result<Post>;
-    async fn create_post(&self, req: CreatePostRequest) -> anyhow::Result<Post>;
-    async fn update_post(&self, id: i64, req: UpdatePostRequest) -> anyhow::Result<Post>;
-    async fn delete_post(&self, id: i64) -> anyhow::Result<()>;
+    /// Returns the current slug of the post that used to have `slug`.
+    async fn find_moved_slug(&self, slug: &str) -> anyhow::Result<Option<String>>;
+    async fn create_post(
+        &self,
+        req: CreatePostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post>;
+    async fn update_post(
+        &self,
+        id: i64,
+        req: UpdatePostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post>;
+    async fn patch_post(
+        &self,
+        id: i64,
+        req: PatchPostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post>;
+    async fn transition_post(
+        &self,
+        id: i64,
+        action: WorkflowAction,
+        comment: Option<String>,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post>;
+    /// Publishes scheduled posts and archives published ones that are due,
+    /// returning the posts that were transitioned.
+    async fn run_schedule(&self, audit: &AuditContext) -> anyhow::Result<Vec<Post>>;
+    /// Posts to be published or archived between `from` and `to`.
+    async fn get_schedule(
+        &self,
+        reader: Reader,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Post>>;
+    /// Revisions of a post, newest first.
+    async fn get_revisions(&self, post_id: i64) -> anyhow::Result<Vec<PostRevision>>;
+    async fn get_revision(&self, post_id: i64, revision: i64) -> anyhow::Result<PostRevision>;
+    /// Sets the title and content of a post back to those of a revision,
+    /// which is recorded as a new revision.
+    async fn restore_revision(
+        &self,
+        post_id: i64,
+        revision: i64,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post>;
+    /// Moves a post to the trash, where it is hidden from all reads.
+    async fn delete_post(&self, id: i64, audit: &AuditContext) -> anyhow::Result<()>;
+    /// Deleted posts the reader owns, most recently deleted first.
+    async fn get_trash(&self, reader: Reader) -> anyhow::Result<Vec<Post>>;
+    async fn get_deleted_post(&self, id: i64) -> anyhow::Result<Post>;
+    async fn restore_post(&self, id: i64, audit: &AuditContext) -> anyhow::Result<Post>;
+    /// Removes posts for good that were deleted before `deleted_before`.
+    async fn purge_posts(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Vec<Post>>;
+}
+
+/// Who is reading posts, which decides the drafts and non-public posts they
+/// can see.
+#[derive(Clone, Copy, Debug)]
+pub enum Reader {
+    Anonymous,
+    /// A logged-in member, who sees their own posts.
+    Author(i64),
+    /// Sees all posts.
+    Editor,
+}
+
+/// Whether a reader may read a post.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Granted,
+    /// Unpublished posts of others are not revealed at all.
+    Denied,
+    LoginRequired,
+    PasswordRequired,
+}
+
+impl Reader {
+    fn author_id(&self) -> Option<i64> {
+        match self {
+            Reader::Author(author_id) => Some(*author_id),
+            _ => None,
+        }
+    }
+
+    /// Authors own their posts, editors own all posts.
+    pub fn owns(&self, post: &Post) -> bool {
+        match self {
+            Reader::Anonymous => false,
+            Reader::Author(author_id) => post.author_id == *author_id,
+            Reader::Editor => true,
+        }
+    }
+
+    pub fn access(&self, post: &Post) -> Access {
+        if self.owns(post) {
+            return Access::Granted;
+        }
+
+        match (post.status, post.visibility) {
+            (status, _) if status != PostStatus::Published => Access::Denied,
+            (_, PostVisibility::Public | PostVisibility::Unlisted) => Access::Granted,
+            (_, PostVisibility::Private) if matches!(self, Reader::Anonymous) => {
+                Access::LoginRequired
+            }
+            (_, PostVisibility::Private) => Access::Granted,
+            (_, PostVisibility::Password) => Access::PasswordRequired,
+        }
+    }
+
+    /// Whether the post shows up in lists. Unlisted and password-protected
+    /// posts are only listed for their authors and editors.
+    pub fn lists(&self, post: &Post) -> bool {
+        self.owns(post)
+            || (post.status == PostStatus::Published
+                && match post.visibility {
+                    PostVisibility::Public => true,
+                    PostVisibility::Private => !matches!(self, Reader::Anonymous),
+                    PostVisibility::Unlisted | PostVisibility::Password => false,
+                })
+    }
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct CreatePostRequest {
+    /// The user the post belongs to, taken from the token of the request.
+    #[serde(skip)]
     pub author_id: i64,
-    pub slug: String,
+    /// Lets editors create a post for another user.
+    #[serde(default, rename = "author_id")]
+    pub on_behalf_of: Option<i64>,
+    /// Generated from the title if not given.
+    pub slug: Option<String>,
     pub title: String,
     pub content: String,
     pub status: PostStatus,
+    #[serde(default)]
+    pub visibility: PostVisibility,
+    /// Required for password-protected posts.
+    pub password: Option<String>,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub unpublish_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -30,31 +175,390 @@ pub struct UpdatePostRequest {
     pub title: String,
     pub content: String,
     pub status: PostStatus,
+    #[serde(default)]
+    pub visibility: PostVisibility,
+    /// Changes the password of a password-protected post, which keeps its
+    /// current password if not given.
+    pub password: Option<String>,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub unpublish_at: Option<DateTime<Utc>>,
+    /// Describes the change in the revision history of the post.
+    pub message: Option<String>,
+    /// The version the update is based on. The update is rejected with a
+    /// `VersionConflict` if the post was changed in the meantime.
+    pub version: Option<i64>,
+}
+
+/// A partial update; fields that are `None` keep their current value.
+#[derive(Default)]
+pub struct PatchPostRequest {
+    pub slug: Option<String>,
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub status: Option<PostStatus>,
+    pub visibility: Option<PostVisibility>,
+    pub password: Option<String>,
+    /// `Some(None)` removes the date.
+    pub publish_at: Option<Option<DateTime<Utc>>>,
+    pub unpublish_at: Option<Option<DateTime<Utc>>>,
+    pub message: Option<String>,
+    pub version: Option<i64>,
+}
+
+impl PatchPostRequest {
+    /// Whether the patch changes nothing; a message alone is not a change.
+    pub fn is_empty(&self) -> bool {
+        self.slug.is_none()
+            && self.title.is_none()
+            && self.content.is_none()
+            && self.status.is_none()
+            && self.visibility.is_none()
+            && self.password.is_none()
+            && self.publish_at.is_none()
+            && self.unpublish_at.is_none()
+    }
+}
+
+/// Returned, wrapped in `anyhow::Error`, when an update is based on an
+/// outdated version of a post. Carries the current state of the post.
+#[derive(Debug)]
+pub struct VersionConflict(pub Post);
+
+impl fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Post {} was modified, current version is {}",
+            self.0.id, self.0.version
+        )
+    }
+}
+
+impl std::error::Error for VersionConflict {}
+
+/// Returned, wrapped in `anyhow::Error`, when a requested slug is used by
+/// another post, currently or in its slug history.
+#[derive(Debug)]
+pub struct SlugTaken(pub String);
+
+impl fmt::Display for SlugTaken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Slug is already taken: {}", self.0)
+    }
+}
+
+impl std::error::Error for SlugTaken {}
+
+/// The steps of the editorial workflow that have their own endpoints.
+#[derive(Clone, Copy, Debug)]
+pub enum WorkflowAction {
+    Submit,
+    Approve,
+    Reject,
+}
+
+impl WorkflowAction {
+    /// Approved posts with a `publish_at` in the future are scheduled.
+    pub fn target(self, post: &Post) -> PostStatus {
+        match self {
+            WorkflowAction::Submit => PostStatus::InReview,
+            WorkflowAction::Approve if post.publish_at.is_some_and(|at| at > Utc::now()) => {
+                PostStatus::Scheduled
+            }
+            WorkflowAction::Approve => PostStatus::Published,
+            WorkflowAction::Reject => PostStatus::ChangesRequested,
+        }
+    }
+
+    fn audit_action(self) -> &'static str {
+        match self {
+            WorkflowAction::Submit => "post.submit",
+            WorkflowAction::Approve => "post.approve",
+            WorkflowAction::Reject => "post.reject",
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RejectPostRequest {
+    /// What the author needs to change, shown with the post.
+    pub comment: String,
+}
+
+/// Returned, wrapped in `anyhow::Error`, when the workflow does not allow a
+/// status change.
+#[derive(Debug)]
+pub struct InvalidTransition {
+    pub from: PostStatus,
+    pub to: PostStatus,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot move post from {:?} to {:?}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+/// Allows keeping the status and the transitions of the workflow.
+fn check_transition(from: PostStatus, to: PostStatus) -> anyhow::Result<()> {
+    match from == to || from.transition(to).is_some() {
+        true => Ok(()),
+        false => Err(InvalidTransition { from, to }.into()),
+    }
+}
+
+/// Scheduled posts need a date to be published at, and posts cannot be
+/// archived before they are published.
+fn check_schedule(
+    status: PostStatus,
+    publish_at: Option<DateTime<Utc>>,
+    unpublish_at: Option<DateTime<Utc>>,
+) -> anyhow::Result<()> {
+    if status == PostStatus::Scheduled && publish_at.is_none() {
+        return Err(InvalidRequest("Scheduled posts require publish_at").into());
+    }
+
+    match (publish_at, unpublish_at) {
+        (Some(publish_at), Some(unpublish_at)) if unpublish_at <= publish_at => {
+            Err(InvalidRequest("unpublish_at must be after publish_at").into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The transition the scheduler makes for a post that is due, with its audit
+/// action.
+fn scheduled_transition(post: &Post, now: DateTime<Utc>) -> Option<(PostStatus, &'static str)> {
+    match post.status {
+        PostStatus::Scheduled if post.publish_at.is_some_and(|at| at <= now) => {
+            Some((PostStatus::Published, "post.publish"))
+        }
+        PostStatus::Published if post.unpublish_at.is_some_and(|at| at <= now) => {
+            Some((PostStatus::Archived, "post.unpublish"))
+        }
+        _ => None,
+    }
+}
+
+/// Returned, wrapped in `anyhow::Error`, when a request is well-formed but
+/// cannot be applied to the post.
+#[derive(Debug)]
+pub struct InvalidRequest(pub &'static str);
+
+impl fmt::Display for InvalidRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for InvalidRequest {}
+
+/// The password hash a post ends up with. Password-protected posts keep their
+/// current password unless a new one is given, other posts have none.
+fn password_hash(
+    visibility: PostVisibility,
+    password: Option<&str>,
+    current: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    match (visibility, password, current) {
+        (PostVisibility::Password, Some(password), _) => Ok(Some(encrypt_password(password)?)),
+        (PostVisibility::Password, None, Some(current)) => Ok(Some(current.to_string())),
+        (PostVisibility::Password, None, None) => {
+            Err(InvalidRequest("A password is required for password-protected posts").into())
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Turns a title into a slug of lowercase ASCII letters, digits and dashes,
+/// transliterating other scripts, e.g. "Grüße aus Köln" to
+/// "grusse-aus-koln". Slugs are never just a number, which `/posts/{id}`
+/// would take for an ID.
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in deunicode::deunicode(title).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    // Leaves room for a numeric suffix within the 255 characters of the
+    // column. The slug is ASCII, so any index is a char boundary.
+    slug.truncate(240);
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() {
+        "post".to_string()
+    } else if slug.bytes().all(|b| b.is_ascii_digit()) {
+        format!("post-{}", slug)
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Rejects slugs from clients that `slugify` would not produce, e.g. with
+/// uppercase letters, spaces, a leading dash or only digits.
+fn check_slug_format(slug: &str) -> anyhow::Result<()> {
+    match slugify(slug) == slug {
+        true => Ok(()),
+        false => Err(InvalidRequest(
+            "Slugs may only contain lowercase letters, digits and single dashes, and must not be a number",
+        )
+        .into()),
+    }
+}
+
+/// Returns `base`, or `base` with the first numeric suffix that is not taken.
+fn dedupe_slug(base: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(base) {
+        return base.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|slug| !taken.contains(slug))
+        .unwrap_or_default()
 }
 
 pub struct InMemoryPostStore {
     pub counter: i64,
     pub items: HashMap<i64, Post>,
+    pub slug_history: HashMap<String, i64>,
+    pub revisions: Vec<PostRevision>,
+    /// Deleted posts, which keep their slugs until they are purged.
+    pub trash: HashMap<i64, Post>,
+    /// How many revisions are kept.
+    pub retention: Revisions,
+}
+
+impl InMemoryPostStore {
+    /// Slugs of all posts other than `post_id`, including their old slugs.
+    fn taken_slugs(&self, post_id: Option<i64>) -> HashSet<String> {
+        let current = self
+            .items
+            .values()
+            .chain(self.trash.values())
+            .filter(|post| Some(post.id) != post_id)
+            .map(|post| post.slug.clone());
+        let history = self
+            .slug_history
+            .iter()
+            .filter(|(_, id)| Some(**id) != post_id)
+            .map(|(slug, _)| slug.clone());
+
+        current.chain(history).collect()
+    }
+
+    fn check_slug(&self, slug: &str, post_id: i64) -> anyhow::Result<()> {
+        if self
+            .items
+            .get(&post_id)
+            .is_some_and(|post| post.slug == slug)
+        {
+            return Ok(());
+        }
+
+        check_slug_format(slug)?;
+        match self.taken_slugs(Some(post_id)).contains(slug) {
+            true => Err(SlugTaken(slug.to_string()).into()),
+            false => Ok(()),
+        }
+    }
+
+    fn add_revision(&mut self, post: &Post, audit: &AuditContext, message: Option<String>) {
+        self.revisions.push(PostRevision {
+            post_id: post.id,
+            revision: post.version,
+            author: audit.actor.clone(),
+            message,
+            title: post.title.clone(),
+            content: post.content.clone(),
+            created: post.updated,
+        });
+        self.prune_revisions(post);
+    }
+
+    /// Removes the revisions of `post` that the retention settings no longer
+    /// keep, like `MySQLPostService::record_revision` does.
+    fn prune_revisions(&mut self, post: &Post) {
+        if let Some(keep) = self.retention.keep {
+            let mut revisions: Vec<i64> = self
+                .revisions
+                .iter()
+                .filter(|revision| revision.post_id == post.id)
+                .map(|revision| revision.revision)
+                .collect();
+            revisions.sort_unstable_by(|a, b| b.cmp(a));
+
+            if let Some(&oldest_removed) = revisions.get(keep.max(1) as usize) {
+                self.revisions.retain(|revision| {
+                    revision.post_id != post.id || revision.revision > oldest_removed
+                });
+            }
+        }
+
+        if let Some(max_age_days) = self.retention.max_age_days {
+            let cutoff = Utc::now().checked_sub_signed(chrono::Duration::days(max_age_days.into()));
+            if let Some(cutoff) = cutoff {
+                self.revisions.retain(|revision| {
+                    revision.post_id != post.id
+                        || revision.revision >= post.version
+                        || revision.created >= cutoff
+                });
+            }
+        }
+    }
+
+    fn move_slug(&mut self, post_id: i64, old: String, new: &str) {
+        if old != new {
+            self.slug_history.remove(new);
+            self.slug_history.insert(old, post_id);
+        }
+    }
 }
 pub struct InMemoryPostService {
     data: Mutex<InMemoryPostStore>,
 }
 
-impl Default for InMemoryPostService {
-    fn default() -> Self {
+impl InMemoryPostService {
+    pub fn new(revisions: &Revisions) -> Self {
         Self {
             data: Mutex::new(InMemoryPostStore {
                 counter: 0,
                 items: Default::default(),
+                slug_history: Default::default(),
+                revisions: Default::default(),
+                trash: Default::default(),
+                retention: revisions.clone(),
             }),
         }
     }
 }
 
+impl Default for InMemoryPostService {
+    fn default() -> Self {
+        Self::new(&Revisions::default())
+    }
+}
+
 impl PostService for InMemoryPostService {
-    async fn get_all_posts(&self) -> anyhow::Result<Vec<Post>> {
+    async fn get_all_posts(
+        &self,
+        reader: Reader,
+        status: Option<PostStatus>,
+    ) -> anyhow::Result<Vec<Post>> {
         let data = self.data.lock().await;
-        Ok(data.items.values().map(|post| (*post).clone()).collect())
+        Ok(data
+            .items
+            .values()
+            .filter(|post| reader.lists(post))
+            .filter(|post| status.map_or(true, |status| post.status == status))
+            .map(|post| (*post).clone())
+            .collect())
     }
 
     async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post> {
@@ -77,21 +581,59 @@ impl PostService for InMemoryPostService {
         anyhow::bail!("Post not found: {}", slug)
     }
 
-    async fn create_post(&self, req: CreatePostRequest) -> anyhow::Result<Post> {
+    async fn find_moved_slug(&self, slug: &str) -> anyhow::Result<Option<String>> {
+        let data = self.data.lock().await;
+
+        Ok(data
+            .slug_history
+            .get(slug)
+            .and_then(|id| data.items.get(id))
+            .map(|post| post.slug.clone()))
+    }
+
+    async fn create_post(
+        &self,
+        req: CreatePostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
         let mut data = self.data.lock().await;
+        let taken = data.taken_slugs(None);
+        let slug = match req.slug {
+            Some(slug) => {
+                check_slug_format(&slug)?;
+                if taken.contains(&slug) {
+                    return Err(SlugTaken(slug).into());
+                }
+                slug
+            }
+            None => dedupe_slug(&slugify(&req.title), &taken),
+        };
+
+        check_transition(PostStatus::Draft, req.status)?;
+        check_schedule(req.status, req.publish_at, req.unpublish_at)?;
+        let password_hash = password_hash(req.visibility, req.password.as_deref(), None)?;
+
         data.counter += 1;
         let ts = chrono::offset::Utc::now();
         let post = Post {
             id: data.counter,
             author_id: req.author_id,
-            slug: req.slug,
+            slug,
             title: req.title,
             content: req.content,
             status: req.status,
+            review_comment: None,
+            publish_at: req.publish_at,
+            unpublish_at: req.unpublish_at,
+            visibility: req.visibility,
+            password_hash,
+            version: 1,
             created: ts,
             updated: ts,
+            deleted_at: None,
         };
 
+        data.add_revision(&post, audit, None);
         data.items.insert(post.id, post);
 
         match data.items.get(&data.counter) {
@@ -102,163 +644,1770 @@ impl PostService for InMemoryPostService {
         }
     }
 
-    async fn update_post(&self, id: i64, req: UpdatePostRequest) -> anyhow::Result<Post> {
+    async fn update_post(
+        &self,
+        id: i64,
+        req: UpdatePostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
         let mut data = self.data.lock().await;
+        let current = data
+            .items
+            .get(&id)
+            .ok_or(anyhow::anyhow!("Post not found: {}", id))?;
+
+        if req
+            .version
+            .is_some_and(|version| version != current.version)
+        {
+            return Err(VersionConflict(current.clone()).into());
+        }
+
+        check_transition(current.status, req.status)?;
+        check_schedule(req.status, req.publish_at, req.unpublish_at)?;
+        let password_hash = password_hash(
+            req.visibility,
+            req.password.as_deref(),
+            current.password_hash.as_deref(),
+        )?;
+        data.check_slug(&req.slug, id)?;
         let post = data
             .items
             .get_mut(&id)
             .ok_or(anyhow::anyhow!("Post not found: {}", id))?;
-
-        post.slug = req.slug;
+        let old_slug = std::mem::replace(&mut post.slug, req.slug);
         post.title = req.title;
         post.content = req.content;
         post.status = req.status;
+        post.publish_at = req.publish_at;
+        post.unpublish_at = req.unpublish_at;
+        post.visibility = req.visibility;
+        post.password_hash = password_hash;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
 
-        match data.items.get(&data.counter) {
+        let post = post.clone();
+        data.move_slug(id, old_slug, &post.slug);
+        data.add_revision(&post, audit, req.message);
+
+        Ok(post)
+    }
+
+    async fn patch_post(
+        &self,
+        id: i64,
+        req: PatchPostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut data = self.data.lock().await;
+        let current = data
+            .items
+            .get(&id)
+            .ok_or(anyhow::anyhow!("Post not found: {}", id))?;
+
+        if req
+            .version
+            .is_some_and(|version| version != current.version)
+        {
+            return Err(VersionConflict(current.clone()).into());
+        }
+
+        if let Some(status) = req.status {
+            check_transition(current.status, status)?;
+        }
+        let publish_at = req.publish_at.unwrap_or(current.publish_at);
+        let unpublish_at = req.unpublish_at.unwrap_or(current.unpublish_at);
+        check_schedule(
+            req.status.unwrap_or(current.status),
+            publish_at,
+            unpublish_at,
+        )?;
+        let visibility = req.visibility.unwrap_or(current.visibility);
+        let password_hash = password_hash(
+            visibility,
+            req.password.as_deref(),
+            current.password_hash.as_deref(),
+        )?;
+        if let Some(slug) = &req.slug {
+            data.check_slug(slug, id)?;
+        }
+        let post = data
+            .items
+            .get_mut(&id)
+            .ok_or(anyhow::anyhow!("Post not found: {}", id))?;
+        let old_slug = post.slug.clone();
+        if let Some(slug) = req.slug {
+            post.slug = slug;
+        }
+        if let Some(title) = req.title {
+            post.title = title;
+        }
+        if let Some(content) = req.content {
+            post.content = content;
+        }
+        if let Some(status) = req.status {
+            post.status = status;
+        }
+        post.publish_at = publish_at;
+        post.unpublish_at = unpublish_at;
+        post.visibility = visibility;
+        post.password_hash = password_hash;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
+
+        let post = post.clone();
+        data.move_slug(id, old_slug, &post.slug);
+        data.add_revision(&post, audit, req.message);
+
+        Ok(post)
+    }
+
+    async fn transition_post(
+        &self,
+        id: i64,
+        action: WorkflowAction,
+        comment: Option<String>,
+        _audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut data = self.data.lock().await;
+        let post = data
+            .items
+            .get_mut(&id)
+            .ok_or(anyhow::anyhow!("Post not found: {}", id))?;
+
+        let to = action.target(post);
+        if post.status.transition(to).is_none() {
+            return Err(InvalidTransition {
+                from: post.status,
+                to,
+            }
+            .into());
+        }
+
+        post.status = to;
+        post.review_comment = comment;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
+
+        Ok(post.clone())
+    }
+
+    async fn run_schedule(&self, _audit: &AuditContext) -> anyhow::Result<Vec<Post>> {
+        let mut data = self.data.lock().await;
+        let now = chrono::offset::Utc::now();
+        let mut posts = Vec::new();
+
+        for post in data.items.values_mut() {
+            if let Some((status, _)) = scheduled_transition(post, now) {
+                post.status = status;
+                post.version += 1;
+                post.updated = now;
+                posts.push(post.clone());
+            }
+        }
+
+        Ok(posts)
+    }
+
+    async fn get_schedule(
+        &self,
+        reader: Reader,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Post>> {
+        let data = self.data.lock().await;
+        let in_range = |at: Option<DateTime<Utc>>| at.is_some_and(|at| at >= from && at < to);
+
+        let mut posts: Vec<Post> = data
+            .items
+            .values()
+            .filter(|post| reader.owns(post))
+            .filter(|post| in_range(post.publish_at) || in_range(post.unpublish_at))
+            .cloned()
+            .collect();
+        posts.sort_by_key(|post| post.publish_at.or(post.unpublish_at));
+
+        Ok(posts)
+    }
+
+    async fn get_revisions(&self, post_id: i64) -> anyhow::Result<Vec<PostRevision>> {
+        let data = self.data.lock().await;
+
+        Ok(data
+            .revisions
+            .iter()
+            .rev()
+            .filter(|revision| revision.post_id == post_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_revision(&self, post_id: i64, revision: i64) -> anyhow::Result<PostRevision> {
+        let data = self.data.lock().await;
+
+        data.revisions
+            .iter()
+            .find(|r| r.post_id == post_id && r.revision == revision)
+            .cloned()
+            .ok_or(anyhow::anyhow!(
+                "Revision not found: {} of post {}",
+                revision,
+                post_id
+            ))
+    }
+
+    async fn restore_revision(
+        &self,
+        post_id: i64,
+        revision: i64,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut data = self.data.lock().await;
+        let restored = data
+            .revisions
+            .iter()
+            .find(|r| r.post_id == post_id && r.revision == revision)
+            .cloned()
+            .ok_or(anyhow::anyhow!(
+                "Revision not found: {} of post {}",
+                revision,
+                post_id
+            ))?;
+        let post = data
+            .items
+            .get_mut(&post_id)
+            .ok_or(anyhow::anyhow!("Post not found: {}", post_id))?;
+
+        post.title = restored.title;
+        post.content = restored.content;
+        post.version += 1;
+        post.updated = chrono::offset::Utc::now();
+
+        let post = post.clone();
+        let message = format!("Restored revision {}", revision);
+        data.add_revision(&post, audit, Some(message));
+
+        Ok(post)
+    }
+
+    async fn delete_post(&self, id: i64, _audit: &AuditContext) -> anyhow::Result<()> {
+        let mut data = self.data.lock().await;
+        match data.items.remove(&id) {
             None => {
                 anyhow::bail!("Post not found: {}", id)
             }
+            Some(mut post) => {
+                post.deleted_at = Some(chrono::offset::Utc::now());
+                data.trash.insert(id, post);
+                Ok(())
+            }
+        }
+    }
+
+    async fn get_trash(&self, reader: Reader) -> anyhow::Result<Vec<Post>> {
+        let data = self.data.lock().await;
+        let mut posts: Vec<Post> = data
+            .trash
+            .values()
+            .filter(|post| reader.owns(post))
+            .cloned()
+            .collect();
+        posts.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+        Ok(posts)
+    }
+
+    async fn get_deleted_post(&self, id: i64) -> anyhow::Result<Post> {
+        let data = self.data.lock().await;
+        match data.trash.get(&id) {
             Some(post) => Ok(post.clone()),
+            None => anyhow::bail!("Post not found in trash: {}", id),
         }
     }
 
-    async fn delete_post(&self, id: i64) -> anyhow::Result<()> {
+    async fn restore_post(&self, id: i64, _audit: &AuditContext) -> anyhow::Result<Post> {
         let mut data = self.data.lock().await;
-        match data.items.remove(&id) {
+        match data.trash.remove(&id) {
             None => {
                 anyhow::bail!("Post not found: {}", id)
             }
-            Some(_) => Ok(()),
+            Some(mut post) => {
+                post.deleted_at = None;
+                data.items.insert(id, post.clone());
+                Ok(post)
+            }
+        }
+    }
+
+    async fn purge_posts(
+        &self,
+        deleted_before: DateTime<Utc>,
+        _audit: &AuditContext,
+    ) -> anyhow::Result<Vec<Post>> {
+        let mut data = self.data.lock().await;
+        let ids: Vec<i64> = data
+            .trash
+            .values()
+            .filter(|post| post.deleted_at.map_or(false, |at| at < deleted_before))
+            .map(|post| post.id)
+            .collect();
+
+        let mut posts = Vec::with_capacity(ids.len());
+        for id in ids {
+            data.slug_history.retain(|_, post_id| *post_id != id);
+            data.revisions.retain(|revision| revision.post_id != id);
+            posts.extend(data.trash.remove(&id));
         }
+
+        Ok(posts)
     }
 }
 
 pub struct MySQLPostService {
     pub pool: MySqlPool,
+    observer: QueryObserver,
+    revisions: Revisions,
 }
 
 impl MySQLPostService {
-    pub fn new(pool: MySqlPool) -> Self {
-        Self { pool }
+    pub fn new(pool: MySqlPool, settings: &Database, revisions: &Revisions) -> Self {
+        Self {
+            pool,
+            observer: QueryObserver::new(settings),
+            revisions: revisions.clone(),
+        }
     }
 }
 
 impl PostService for MySQLPostService {
-    async fn get_all_posts(&self) -> anyhow::Result<Vec<Post>> {
-        todo!()
-    }
+    #[instrument(skip(self))]
+    async fn get_all_posts(
+        &self,
+        reader: Reader,
+        status: Option<PostStatus>,
+    ) -> anyhow::Result<Vec<Post>> {
+        // Mirrors `Reader::lists`.
+        let all = matches!(reader, Reader::Editor);
+        let member = !matches!(reader, Reader::Anonymous);
+        let author_id = reader.author_id();
+        let status = status.map(i32::from);
 
-    async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post> {
-        let res = sqlx::query!(
+        let res = sqlx::query_as!(
+            PostRow,
             r#"
-                SELECT id, author_id, slug, title, content, status, created, updated
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
                 FROM posts
-                WHERE id = ?
+                WHERE (? OR author_id = ?
+                        OR (status = ? AND (visibility = ? OR (visibility = ? AND ?))))
+                    AND (? IS NULL OR status = ?)
+                    AND deleted_at IS NULL
+                ORDER BY id
             "#,
-            id
+            all,
+            author_id,
+            i32::from(PostStatus::Published),
+            i32::from(PostVisibility::Public),
+            i32::from(PostVisibility::Private),
+            member,
+            status,
+            status
         );
 
-        res.fetch_one(&self.pool)
+        self.observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&self.pool),
+            )
             .await
-            .map(|row| Post {
-                id: row.id as i64,
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-                author_id: row.author_id as i64,
-                slug: row.slug,
-                title: row.title,
-                content: row.content,
-                status: PostStatus::from(row.status),
-            })
-            .map_err(|e| anyhow::anyhow!(e).context(format!("Failed to get post by id: {}", id)))
+            .and_then(|rows| rows.into_iter().map(Post::try_from).collect())
+            .map_err(|e| anyhow::anyhow!(e).context("Failed to get posts"))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_post_by_id(&self, id: i64) -> anyhow::Result<Post> {
+        self.select_post(&self.pool, id).await
     }
 
+    #[instrument(skip(self))]
     async fn get_post_by_slug(&self, name: &str) -> anyhow::Result<Post> {
-        let res = sqlx::query!(
+        let res = sqlx::query_as!(
+            PostRow,
             r#"
-                SELECT id, author_id, slug, title, content, status, created, updated
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
                 FROM posts
-                WHERE slug = ?
+                WHERE slug = ? AND deleted_at IS NULL
             "#,
             name
         );
 
-        res.fetch_one(&self.pool)
+        self.observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |_| 1,
+                res.fetch_one(&self.pool),
+            )
             .await
-            .map(|row| Post {
-                id: row.id as i64,
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-                author_id: row.author_id as i64,
-                slug: row.slug,
-                title: row.title,
-                content: row.content,
-                status: PostStatus::from(row.status),
-            })
+            .and_then(Post::try_from)
             .map_err(|e| {
                 anyhow::anyhow!(e).context(format!("Failed to get post by slug: {}", name))
             })
     }
 
-    async fn create_post(&self, req: CreatePostRequest) -> anyhow::Result<Post> {
+    #[instrument(skip(self))]
+    async fn find_moved_slug(&self, slug: &str) -> anyhow::Result<Option<String>> {
         let res = sqlx::query!(
             r#"
-                INSERT INTO posts (author_id, slug, title, content, status, created, updated)
-                VALUES (?, ?, ?, ?, ?, NOW(), NOW())
+                SELECT posts.slug
+                FROM post_slug_history
+                JOIN posts ON posts.id = post_slug_history.post_id
+                WHERE post_slug_history.slug = ? AND posts.deleted_at IS NULL
             "#,
-            req.author_id,
-            req.slug,
-            req.title,
-            req.content,
-            i32::from(req.status)
-        )
-        .execute(&self.pool)
-        .await?
-        .last_insert_id();
-
-        let id: i64 = res
-            .try_into()
-            .or_else(|_| anyhow::bail!("Failed to convert post id"))?;
+            slug
+        );
 
-        self.get_post_by_id(id).await
+        self.observer
+            .observe(
+                "SELECT",
+                "post_slug_history",
+                res.sql(),
+                |row| row.is_some() as u64,
+                res.fetch_optional(&self.pool),
+            )
+            .await
+            .map(|row| row.map(|row| row.slug))
+            .map_err(|e| anyhow::anyhow!(e).context(format!("Failed to look up slug: {}", slug)))
     }
 
-    async fn update_post(&self, id: i64, req: UpdatePostRequest) -> anyhow::Result<Post> {
-        let res = sqlx::query!(
-            r#"
-                UPDATE posts
-                SET slug = ?, title = ?, content = ?, status = ?, updated = NOW()
-                WHERE id = ?
+    #[instrument(skip(self, req, audit))]
+    async fn create_post(
+        &self,
+        req: CreatePostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut tx = self.pool.begin().await?;
+
+        let slug = match req.slug {
+            Some(slug) => {
+                check_slug_format(&slug)?;
+                if self
+                    .taken_slugs(&mut tx, &slug, None)
+                    .await?
+                    .contains(&slug)
+                {
+                    return Err(SlugTaken(slug).into());
+                }
+                slug
+            }
+            None => {
+                let base = slugify(&req.title);
+                dedupe_slug(&base, &self.taken_slugs(&mut tx, &base, None).await?)
+            }
+        };
+
+        check_transition(PostStatus::Draft, req.status)?;
+        check_schedule(req.status, req.publish_at, req.unpublish_at)?;
+        let password_hash = password_hash(req.visibility, req.password.as_deref(), None)?;
+
+        let query = sqlx::query!(
+            r#"
+                INSERT INTO posts
+                    (author_id, slug, title, content, status, publish_at, unpublish_at,
+                    visibility, password_hash, created, updated)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, NOW(), NOW())
+            "#,
+            req.author_id,
+            slug,
+            req.title,
+            req.content,
+            i32::from(req.status),
+            req.publish_at,
+            req.unpublish_at,
+            i32::from(req.visibility),
+            password_hash
+        );
+
+        let res = self
+            .observer
+            .observe(
+                "INSERT",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await
+            .map_err(|e| slug_error(e, &slug))?
+            .last_insert_id();
+
+        let id: i64 = res
+            .try_into()
+            .or_else(|_| anyhow::bail!("Failed to convert post id"))?;
+
+        let post = self.select_post(&mut *tx, id).await?;
+        self.record_revision(&mut tx, &post, audit, None).await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "post.create",
+                target_type: "post",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(None, Some(&post), &[]),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    #[instrument(skip(self, req, audit))]
+    async fn update_post(
+        &self,
+        id: i64,
+        req: UpdatePostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_post(&mut tx, id).await?;
+
+        // The row is locked, so the version cannot change before the update.
+        if req.version.is_some_and(|version| version != before.version) {
+            return Err(VersionConflict(before).into());
+        }
+
+        check_transition(before.status, req.status)?;
+        check_schedule(req.status, req.publish_at, req.unpublish_at)?;
+        self.check_slug(&mut tx, &req.slug, &before).await?;
+        let password_hash = password_hash(
+            req.visibility,
+            req.password.as_deref(),
+            before.password_hash.as_deref(),
+        )?;
+
+        let query = sqlx::query!(
+            r#"
+                UPDATE posts
+                SET slug = ?, title = ?, content = ?, status = ?, publish_at = ?,
+                    unpublish_at = ?, visibility = ?, password_hash = ?, version = version + 1,
+                    updated = NOW()
+                WHERE id = ?
             "#,
             req.slug,
             req.title,
             req.content,
             i32::from(req.status),
+            req.publish_at,
+            req.unpublish_at,
+            i32::from(req.visibility),
+            password_hash,
             id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await
+            .map_err(|e| slug_error(e, &req.slug))?;
+
+        let post = self.select_post(&mut *tx, id).await?;
+
+        self.move_slug(&mut tx, id, &before.slug, &post.slug)
+            .await?;
+        self.record_revision(&mut tx, &post, audit, req.message.as_deref())
+            .await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "post.update",
+                target_type: "post",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&post), &[]),
+            },
         )
-        .execute(&self.pool)
         .await?;
 
-        if res.rows_affected() == 0 {
-            anyhow::bail!("Post not found: {}", id)
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    #[instrument(skip(self, req, audit))]
+    async fn patch_post(
+        &self,
+        id: i64,
+        req: PatchPostRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_post(&mut tx, id).await?;
+
+        if req.version.is_some_and(|version| version != before.version) {
+            return Err(VersionConflict(before).into());
         }
 
-        let user = self.get_post_by_id(id).await?;
+        if let Some(status) = req.status {
+            check_transition(before.status, status)?;
+        }
+        let publish_at = req.publish_at.unwrap_or(before.publish_at);
+        let unpublish_at = req.unpublish_at.unwrap_or(before.unpublish_at);
+        check_schedule(
+            req.status.unwrap_or(before.status),
+            publish_at,
+            unpublish_at,
+        )?;
+        if let Some(slug) = &req.slug {
+            self.check_slug(&mut tx, slug, &before).await?;
+        }
+        let visibility = req.visibility.unwrap_or(before.visibility);
+        let password_hash = password_hash(
+            visibility,
+            req.password.as_deref(),
+            before.password_hash.as_deref(),
+        )?;
+
+        let query = sqlx::query!(
+            r#"
+                UPDATE posts
+                SET slug = COALESCE(?, slug), title = COALESCE(?, title),
+                    content = COALESCE(?, content), status = COALESCE(?, status),
+                    publish_at = ?, unpublish_at = ?, visibility = ?, password_hash = ?,
+                    version = version + 1, updated = NOW()
+                WHERE id = ?
+            "#,
+            req.slug,
+            req.title,
+            req.content,
+            req.status.map(i32::from),
+            publish_at,
+            unpublish_at,
+            i32::from(visibility),
+            password_hash,
+            id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await
+            .map_err(|e| slug_error(e, req.slug.as_deref().unwrap_or_default()))?;
+
+        let post = self.select_post(&mut *tx, id).await?;
+
+        self.move_slug(&mut tx, id, &before.slug, &post.slug)
+            .await?;
+        self.record_revision(&mut tx, &post, audit, req.message.as_deref())
+            .await?;
 
-        Ok(user)
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "post.update",
+                target_type: "post",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&post), &[]),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(post)
     }
 
-    async fn delete_post(&self, id: i64) -> anyhow::Result<()> {
-        sqlx::query!(
+    #[instrument(skip(self, comment, audit))]
+    async fn transition_post(
+        &self,
+        id: i64,
+        action: WorkflowAction,
+        comment: Option<String>,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_post(&mut tx, id).await?;
+
+        let to = action.target(&before);
+        if before.status.transition(to).is_none() {
+            return Err(InvalidTransition {
+                from: before.status,
+                to,
+            }
+            .into());
+        }
+
+        let query = sqlx::query!(
             r#"
-                DELETE FROM posts
+                UPDATE posts
+                SET status = ?, review_comment = ?, version = version + 1, updated = NOW()
                 WHERE id = ?
             "#,
+            i32::from(to),
+            comment,
             id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?;
+
+        let post = self.select_post(&mut *tx, id).await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: action.audit_action(),
+                target_type: "post",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&post), &[]),
+            },
         )
-        .execute(&self.pool)
         .await?;
 
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    #[instrument(skip(self, audit))]
+    async fn run_schedule(&self, audit: &AuditContext) -> anyhow::Result<Vec<Post>> {
+        let mut tx = self.pool.begin().await?;
+
+        // Posts locked by another replica running the schedule are skipped,
+        // so every due post is transitioned exactly once.
+        let res = sqlx::query!(
+            r#"
+                SELECT id
+                FROM posts
+                WHERE ((status = ? AND publish_at <= NOW())
+                        OR (status = ? AND unpublish_at <= NOW()))
+                    AND deleted_at IS NULL
+                ORDER BY id
+                LIMIT 100
+                FOR UPDATE SKIP LOCKED
+            "#,
+            i32::from(PostStatus::Scheduled),
+            i32::from(PostStatus::Published)
+        );
+
+        let due = self
+            .observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&mut *tx),
+            )
+            .await?;
+
+        let now = Utc::now();
+        let mut posts = Vec::with_capacity(due.len());
+
+        for row in due {
+            let id = row.id as i64;
+            let before = self.select_post(&mut *tx, id).await?;
+            let Some((status, action)) = scheduled_transition(&before, now) else {
+                continue;
+            };
+
+            let query = sqlx::query!(
+                r#"
+                    UPDATE posts
+                    SET status = ?, version = version + 1, updated = NOW()
+                    WHERE id = ?
+                "#,
+                i32::from(status),
+                id
+            );
+
+            self.observer
+                .observe(
+                    "UPDATE",
+                    "posts",
+                    query.sql(),
+                    |r| r.rows_affected(),
+                    query.execute(&mut *tx),
+                )
+                .await?;
+
+            let post = self.select_post(&mut *tx, id).await?;
+
+            audit::record_event(
+                &mut tx,
+                &self.observer,
+                audit,
+                NewAuditEvent {
+                    action,
+                    target_type: "post",
+                    target_id: Some(id.to_string()),
+                    changes: audit::diff(Some(&before), Some(&post), &[]),
+                },
+            )
+            .await?;
+
+            posts.push(post);
+        }
+
+        tx.commit().await?;
+
+        Ok(posts)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_schedule(
+        &self,
+        reader: Reader,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Post>> {
+        let all = matches!(reader, Reader::Editor);
+        let author_id = reader.author_id();
+
+        let res = sqlx::query_as!(
+            PostRow,
+            r#"
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
+                FROM posts
+                WHERE (? OR author_id = ?)
+                    AND deleted_at IS NULL
+                    AND ((publish_at >= ? AND publish_at < ?)
+                        OR (unpublish_at >= ? AND unpublish_at < ?))
+                ORDER BY COALESCE(publish_at, unpublish_at), id
+            "#,
+            all,
+            author_id,
+            from,
+            to,
+            from,
+            to
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&self.pool),
+            )
+            .await
+            .and_then(|rows| rows.into_iter().map(Post::try_from).collect())
+            .map_err(|e| anyhow::anyhow!(e).context("Failed to get post schedule"))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_revisions(&self, post_id: i64) -> anyhow::Result<Vec<PostRevision>> {
+        let res = sqlx::query!(
+            r#"
+                SELECT post_id, revision, author, message, title, content, created
+                FROM post_revisions
+                WHERE post_id = ?
+                ORDER BY revision DESC
+            "#,
+            post_id
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "post_revisions",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&self.pool),
+            )
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| PostRevision {
+                        post_id: row.post_id as i64,
+                        revision: row.revision as i64,
+                        author: row.author,
+                        message: row.message,
+                        title: row.title,
+                        content: row.content,
+                        created: row.created,
+                    })
+                    .collect()
+            })
+            .map_err(|e| {
+                anyhow::anyhow!(e).context(format!("Failed to get revisions of post {}", post_id))
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_revision(&self, post_id: i64, revision: i64) -> anyhow::Result<PostRevision> {
+        self.select_revision(&self.pool, post_id, revision).await
+    }
+
+    #[instrument(skip(self, audit))]
+    async fn restore_revision(
+        &self,
+        post_id: i64,
+        revision: i64,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Post> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_post(&mut tx, post_id).await?;
+        let restored = self.select_revision(&mut *tx, post_id, revision).await?;
+
+        let query = sqlx::query!(
+            r#"
+                UPDATE posts
+                SET title = ?, content = ?, version = version + 1, updated = NOW()
+                WHERE id = ?
+            "#,
+            restored.title,
+            restored.content,
+            post_id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?;
+
+        let post = self.select_post(&mut *tx, post_id).await?;
+        let message = format!("Restored revision {}", revision);
+        self.record_revision(&mut tx, &post, audit, Some(&message))
+            .await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "post.revision_restore",
+                target_type: "post",
+                target_id: Some(post_id.to_string()),
+                changes: audit::diff(Some(&before), Some(&post), &[]),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    #[instrument(skip(self, audit))]
+    async fn delete_post(&self, id: i64, audit: &AuditContext) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_post(&mut tx, id).await?;
+
+        let query = sqlx::query!(
+            r#"
+                UPDATE posts
+                SET deleted_at = NOW()
+                WHERE id = ?
+            "#,
+            id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?;
+
+        let after = Post {
+            deleted_at: Some(Utc::now()),
+            ..before.clone()
+        };
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "post.delete",
+                target_type: "post",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&after), &[]),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    async fn get_trash(&self, reader: Reader) -> anyhow::Result<Vec<Post>> {
+        let all = matches!(reader, Reader::Editor);
+        let author_id = reader.author_id();
+
+        let res = sqlx::query_as!(
+            PostRow,
+            r#"
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
+                FROM posts
+                WHERE deleted_at IS NOT NULL AND (? OR author_id = ?)
+                ORDER BY deleted_at DESC, id
+            "#,
+            all,
+            author_id
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&self.pool),
+            )
+            .await
+            .and_then(|rows| rows.into_iter().map(Post::try_from).collect())
+            .map_err(|e| anyhow::anyhow!(e).context("Failed to get deleted posts"))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_deleted_post(&self, id: i64) -> anyhow::Result<Post> {
+        let res = sqlx::query_as!(
+            PostRow,
+            r#"
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
+                FROM posts
+                WHERE id = ? AND deleted_at IS NOT NULL
+            "#,
+            id
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |row| row.is_some() as u64,
+                res.fetch_optional(&self.pool),
+            )
+            .await?
+            .map(Post::try_from)
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("Post not found in trash: {}", id))
+    }
+
+    #[instrument(skip(self, audit))]
+    async fn restore_post(&self, id: i64, audit: &AuditContext) -> anyhow::Result<Post> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_deleted_post(&mut tx, id).await?;
+
+        let query = sqlx::query!(
+            r#"
+                UPDATE posts
+                SET deleted_at = NULL
+                WHERE id = ?
+            "#,
+            id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "posts",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?;
+
+        let post = self.select_post(&mut *tx, id).await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "post.restore",
+                target_type: "post",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&post), &[]),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    #[instrument(skip(self, audit))]
+    async fn purge_posts(
+        &self,
+        deleted_before: DateTime<Utc>,
+        audit: &AuditContext,
+    ) -> anyhow::Result<Vec<Post>> {
+        let mut tx = self.pool.begin().await?;
+
+        let res = sqlx::query!(
+            r#"
+                SELECT id
+                FROM posts
+                WHERE deleted_at < ?
+                ORDER BY id
+                FOR UPDATE
+            "#,
+            deleted_before
+        );
+
+        let expired = self
+            .observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&mut *tx),
+            )
+            .await?;
+
+        let mut posts = Vec::with_capacity(expired.len());
+
+        for row in expired {
+            let id = row.id as i64;
+            let before = self.lock_deleted_post(&mut tx, id).await?;
+
+            // Slug history and revisions go with the post.
+            let query = sqlx::query!(
+                r#"
+                    DELETE FROM posts
+                    WHERE id = ?
+                "#,
+                id
+            );
+
+            self.observer
+                .observe(
+                    "DELETE",
+                    "posts",
+                    query.sql(),
+                    |r| r.rows_affected(),
+                    query.execute(&mut *tx),
+                )
+                .await?;
+
+            audit::record_event(
+                &mut tx,
+                &self.observer,
+                audit,
+                NewAuditEvent {
+                    action: "post.purge",
+                    target_type: "post",
+                    target_id: Some(id.to_string()),
+                    changes: audit::diff(Some(&before), None, &[]),
+                },
+            )
+            .await?;
+
+            posts.push(before);
+        }
+
+        tx.commit().await?;
+
+        Ok(posts)
+    }
+}
+
+impl MySQLPostService {
+    async fn select_post<'e, E>(&self, executor: E, id: i64) -> anyhow::Result<Post>
+    where
+        E: Executor<'e, Database = MySql>,
+    {
+        let res = sqlx::query_as!(
+            PostRow,
+            r#"
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
+                FROM posts
+                WHERE id = ? AND deleted_at IS NULL
+            "#,
+            id
+        );
+
+        self.observer
+            .observe("SELECT", "posts", res.sql(), |_| 1, res.fetch_one(executor))
+            .await
+            .and_then(Post::try_from)
+            .map_err(|e| anyhow::anyhow!(e).context(format!("Failed to get post by id: {}", id)))
+    }
+
+    /// Slugs equal to `base` or starting with `base-` that are used by posts
+    /// other than `post_id`, currently or in their slug history.
+    async fn taken_slugs(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        base: &str,
+        post_id: Option<i64>,
+    ) -> anyhow::Result<HashSet<String>> {
+        let pattern = format!(
+            "{}-%",
+            base.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let res = sqlx::query!(
+            r#"
+                SELECT slug
+                FROM posts
+                WHERE (slug = ? OR slug LIKE ?) AND (? IS NULL OR id <> ?)
+                UNION
+                SELECT slug
+                FROM post_slug_history
+                WHERE (slug = ? OR slug LIKE ?) AND (? IS NULL OR post_id <> ?)
+            "#,
+            base,
+            pattern,
+            post_id,
+            post_id,
+            base,
+            pattern,
+            post_id,
+            post_id
+        );
+
+        let rows = self
+            .observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |rows| rows.len() as u64,
+                res.fetch_all(&mut **tx),
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.slug).collect())
+    }
+
+    async fn check_slug(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        slug: &str,
+        post: &Post,
+    ) -> anyhow::Result<()> {
+        if slug == post.slug {
+            return Ok(());
+        }
+
+        check_slug_format(slug)?;
+        match self
+            .taken_slugs(tx, slug, Some(post.id))
+            .await?
+            .contains(slug)
+        {
+            true => Err(SlugTaken(slug.to_string()).into()),
+            false => Ok(()),
+        }
+    }
+
+    /// Keeps `old` in the slug history of the post, so links using it can be
+    /// redirected. A post moving back to one of its old slugs takes it out of
+    /// the history again.
+    async fn move_slug(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        post_id: i64,
+        old: &str,
+        new: &str,
+    ) -> anyhow::Result<()> {
+        if old == new {
+            return Ok(());
+        }
+
+        let query = sqlx::query!(
+            r#"
+                DELETE FROM post_slug_history
+                WHERE slug = ? AND post_id = ?
+            "#,
+            new,
+            post_id
+        );
+
+        self.observer
+            .observe(
+                "DELETE",
+                "post_slug_history",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut **tx),
+            )
+            .await?;
+
+        let query = sqlx::query!(
+            r#"
+                INSERT INTO post_slug_history (slug, post_id, created)
+                VALUES (?, ?, NOW())
+            "#,
+            old,
+            post_id
+        );
+
+        self.observer
+            .observe(
+                "INSERT",
+                "post_slug_history",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut **tx),
+            )
+            .await
+            .map_err(|e| slug_error(e, old))?;
+
+        Ok(())
+    }
+
+    async fn select_revision<'e, E>(
+        &self,
+        executor: E,
+        post_id: i64,
+        revision: i64,
+    ) -> anyhow::Result<PostRevision>
+    where
+        E: Executor<'e, Database = MySql>,
+    {
+        let res = sqlx::query!(
+            r#"
+                SELECT post_id, revision, author, message, title, content, created
+                FROM post_revisions
+                WHERE post_id = ? AND revision = ?
+            "#,
+            post_id,
+            revision
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "post_revisions",
+                res.sql(),
+                |row| row.is_some() as u64,
+                res.fetch_optional(executor),
+            )
+            .await?
+            .map(|row| PostRevision {
+                post_id: row.post_id as i64,
+                revision: row.revision as i64,
+                author: row.author,
+                message: row.message,
+                title: row.title,
+                content: row.content,
+                created: row.created,
+            })
+            .ok_or_else(|| anyhow::anyhow!("Revision not found: {} of post {}", revision, post_id))
+    }
+
+    /// Keeps the title and content of `post` as a revision and removes the
+    /// revisions beyond the retention policy. The revision just written is
+    /// always kept.
+    async fn record_revision(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        post: &Post,
+        audit: &AuditContext,
+        message: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let query = sqlx::query!(
+            r#"
+                INSERT INTO post_revisions (post_id, revision, author, message, title, content, created)
+                VALUES (?, ?, ?, ?, ?, ?, NOW())
+            "#,
+            post.id,
+            post.version,
+            audit.actor,
+            message,
+            post.title,
+            post.content
+        );
+
+        self.observer
+            .observe(
+                "INSERT",
+                "post_revisions",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut **tx),
+            )
+            .await?;
+
+        if let Some(keep) = self.revisions.keep {
+            let res = sqlx::query!(
+                r#"
+                    SELECT revision
+                    FROM post_revisions
+                    WHERE post_id = ?
+                    ORDER BY revision DESC
+                    LIMIT 1 OFFSET ?
+                "#,
+                post.id,
+                keep.max(1)
+            );
+
+            let oldest_removed = self
+                .observer
+                .observe(
+                    "SELECT",
+                    "post_revisions",
+                    res.sql(),
+                    |row| row.is_some() as u64,
+                    res.fetch_optional(&mut **tx),
+                )
+                .await?;
+
+            if let Some(row) = oldest_removed {
+                let query = sqlx::query!(
+                    r#"
+                        DELETE FROM post_revisions
+                        WHERE post_id = ? AND revision <= ?
+                    "#,
+                    post.id,
+                    row.revision
+                );
+
+                self.observer
+                    .observe(
+                        "DELETE",
+                        "post_revisions",
+                        query.sql(),
+                        |r| r.rows_affected(),
+                        query.execute(&mut **tx),
+                    )
+                    .await?;
+            }
+        }
+
+        if let Some(max_age_days) = self.revisions.max_age_days {
+            let query = sqlx::query!(
+                r#"
+                    DELETE FROM post_revisions
+                    WHERE post_id = ? AND revision < ?
+                        AND created < NOW() - INTERVAL ? DAY
+                "#,
+                post.id,
+                post.version,
+                max_age_days
+            );
+
+            self.observer
+                .observe(
+                    "DELETE",
+                    "post_revisions",
+                    query.sql(),
+                    |r| r.rows_affected(),
+                    query.execute(&mut **tx),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the current state of a post and keeps it locked until `tx` ends,
+    /// so the audit record describes exactly the change that was made.
+    async fn lock_post(&self, tx: &mut Transaction<'_, MySql>, id: i64) -> anyhow::Result<Post> {
+        self.lock(tx, id, false).await
+    }
+
+    async fn lock_deleted_post(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        id: i64,
+    ) -> anyhow::Result<Post> {
+        self.lock(tx, id, true).await
+    }
+
+    async fn lock(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        id: i64,
+        deleted: bool,
+    ) -> anyhow::Result<Post> {
+        let res = sqlx::query_as!(
+            PostRow,
+            r#"
+                SELECT id, author_id, slug, title, content, status, review_comment, publish_at,
+                    unpublish_at, visibility, password_hash, version, created, updated, deleted_at
+                FROM posts
+                WHERE id = ? AND (deleted_at IS NOT NULL) = ?
+                FOR UPDATE
+            "#,
+            id,
+            deleted
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "posts",
+                res.sql(),
+                |row| row.is_some() as u64,
+                res.fetch_optional(&mut **tx),
+            )
+            .await?
+            .map(Post::try_from)
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("Post not found: {}", id))
+    }
+}
+
+/// A row of `posts` as selected by `query_as!`, before the status and
+/// visibility are decoded.
+struct PostRow {
+    id: i32,
+    author_id: i32,
+    slug: String,
+    title: String,
+    content: String,
+    status: i32,
+    review_comment: Option<String>,
+    publish_at: Option<DateTime<Utc>>,
+    unpublish_at: Option<DateTime<Utc>>,
+    visibility: i32,
+    password_hash: Option<String>,
+    version: i32,
+    created: Option<DateTime<Utc>>,
+    updated: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<PostRow> for Post {
+    type Error = sqlx::Error;
+
+    fn try_from(row: PostRow) -> Result<Self, Self::Error> {
+        Ok(Post {
+            id: row.id as i64,
+            created: row.created.unwrap_or_default(),
+            updated: row.updated.unwrap_or_default(),
+            author_id: row.author_id as i64,
+            slug: row.slug,
+            title: row.title,
+            content: row.content,
+            status: decode_status(row.status)?,
+            review_comment: row.review_comment,
+            publish_at: row.publish_at,
+            unpublish_at: row.unpublish_at,
+            visibility: decode_visibility(row.visibility)?,
+            password_hash: row.password_hash,
+            version: row.version as i64,
+            deleted_at: row.deleted_at,
+        })
+    }
+}
+
+/// Fails on status values that no `PostStatus` stands for, instead of
+/// guessing what they mean.
+fn decode_status(value: i32) -> Result<PostStatus, sqlx::Error> {
+    PostStatus::try_from(value).map_err(|e| sqlx::Error::Decode(e.into()))
+}
+
+/// Fails on visibility values that no `PostVisibility` stands for, instead
+/// of serving the post with a visibility nobody chose.
+fn decode_visibility(value: i32) -> Result<PostVisibility, sqlx::Error> {
+    PostVisibility::try_from(value).map_err(|e| sqlx::Error::Decode(e.into()))
+}
+
+/// Turns a violated unique constraint into `SlugTaken`, for concurrent writes
+/// that both passed the check for the same slug.
+fn slug_error(e: sqlx::Error, slug: &str) -> anyhow::Error {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => SlugTaken(slug.to_string()).into(),
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_slug_format, dedupe_slug, slugify, InMemoryPostStore};
+    use crate::model::{Post, PostRevision, PostStatus, PostVisibility};
+    use crate::services::audit::AuditContext;
+    use crate::settings::Revisions;
+    use chrono::{Duration, Utc};
+    use std::collections::HashSet;
+
+    #[test]
+    fn slugify_lowercases_and_joins_words_with_dashes() {
+        assert_eq!(slugify("Hello,  World!"), "hello-world");
+        assert_eq!(slugify("  --Rust 2024--  "), "rust-2024");
+    }
+
+    #[test]
+    fn slugify_transliterates() {
+        assert_eq!(slugify("Grüße aus Köln"), "grusse-aus-koln");
+    }
+
+    #[test]
+    fn slugify_never_returns_a_number() {
+        assert_eq!(slugify("2024"), "post-2024");
+        assert_eq!(slugify("2024 in review"), "2024-in-review");
+    }
+
+    #[test]
+    fn slugify_falls_back_for_empty_titles() {
+        assert_eq!(slugify(""), "post");
+        assert_eq!(slugify("?!"), "post");
+    }
+
+    #[test]
+    fn slugify_truncates_long_titles() {
+        let slug = slugify(&"a ".repeat(200));
+        assert!(slug.len() <= 240);
+        assert!(!slug.ends_with('-'));
+    }
+
+    #[test]
+    fn dedupe_slug_appends_first_free_suffix() {
+        let taken: HashSet<String> = ["post", "post-2", "post-4"]
+            .iter()
+            .map(|slug| slug.to_string())
+            .collect();
+
+        assert_eq!(dedupe_slug("other", &taken), "other");
+        assert_eq!(dedupe_slug("post", &taken), "post-3");
+    }
+
+    #[test]
+    fn rejects_slugs_slugify_would_not_produce() {
+        assert!(check_slug_format("hello-world-2").is_ok());
+        for slug in [
+            "",
+            "Hello",
+            "hello world",
+            "-hello",
+            "hello-",
+            "a--b",
+            "grüße",
+            "2024",
+        ] {
+            assert!(check_slug_format(slug).is_err(), "{}", slug);
+        }
+    }
+
+    fn store(retention: Revisions) -> InMemoryPostStore {
+        InMemoryPostStore {
+            counter: 0,
+            items: Default::default(),
+            slug_history: Default::default(),
+            revisions: Default::default(),
+            trash: Default::default(),
+            retention,
+        }
+    }
+
+    fn post(id: i64, version: i64) -> Post {
+        let now = Utc::now();
+        Post {
+            id,
+            author_id: 1,
+            slug: format!("post-{}", id),
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            status: PostStatus::Draft,
+            review_comment: None,
+            publish_at: None,
+            unpublish_at: None,
+            visibility: PostVisibility::Public,
+            password_hash: None,
+            version,
+            created: now,
+            updated: now,
+            deleted_at: None,
+        }
+    }
+
+    fn revisions(store: &InMemoryPostStore, post_id: i64) -> Vec<i64> {
+        store
+            .revisions
+            .iter()
+            .filter(|revision| revision.post_id == post_id)
+            .map(|revision| revision.revision)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_revisions_of_each_post() {
+        let mut store = store(Revisions {
+            keep: Some(2),
+            max_age_days: None,
+        });
+        store.add_revision(&post(2, 1), &AuditContext::default(), None);
+        for version in 1..=4 {
+            store.add_revision(&post(1, version), &AuditContext::default(), None);
+        }
+
+        assert_eq!(revisions(&store, 1), vec![3, 4]);
+        assert_eq!(revisions(&store, 2), vec![1]);
+    }
+
+    #[test]
+    fn removes_revisions_older_than_max_age() {
+        let mut store = store(Revisions {
+            keep: None,
+            max_age_days: Some(30),
+        });
+        for (revision, age_days) in [(1, 60), (2, 10)] {
+            store.revisions.push(PostRevision {
+                post_id: 1,
+                revision,
+                author: None,
+                message: None,
+                title: "Title".to_string(),
+                content: "Content".to_string(),
+                created: Utc::now() - Duration::days(age_days),
+            });
+        }
+        store.add_revision(&post(1, 3), &AuditContext::default(), None);
+
+        assert_eq!(revisions(&store, 1), vec![2, 3]);
+    }
 }
@@ -0,0 +1,182 @@
+use crate::settings::Database;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::Instrument;
+
+/// Wraps database calls in spans following the OpenTelemetry database
+/// semantic conventions and warns about queries slower than the threshold.
+#[derive(Clone, Debug)]
+pub struct QueryObserver {
+    slow_query_threshold: Duration,
+}
+
+impl QueryObserver {
+    pub fn new(settings: &Database) -> Self {
+        Self {
+            slow_query_threshold: Duration::from_millis(
+                settings.slow_query_threshold_ms.unwrap_or(500),
+            ),
+        }
+    }
+
+    pub async fn observe<T, F>(
+        &self,
+        operation: &'static str,
+        table: &'static str,
+        statement: &str,
+        rows: impl FnOnce(&T) -> u64,
+        query: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let statement = sanitize_statement(statement);
+
+        let span = tracing::info_span!(
+            "db_query",
+            otel.name = format!("{} {}", operation, table),
+            otel.kind = "client",
+            otel.status_code = Empty,
+            otel.status_message = Empty,
+            db.system = "mysql",
+            db.operation = operation,
+            db.sql.table = table,
+            db.statement = statement.as_str(),
+            db.response.returned_rows = Empty,
+            db.rows_affected = Empty,
+        );
+
+        let start = Instant::now();
+        let result = query.instrument(span.clone()).await;
+        let elapsed = start.elapsed();
+
+        match &result {
+            Ok(value) if operation == "SELECT" => {
+                span.record("db.response.returned_rows", rows(value));
+            }
+            Ok(value) => {
+                span.record("db.rows_affected", rows(value));
+            }
+            Err(e) => {
+                span.record("otel.status_code", "ERROR");
+                span.record("otel.status_message", e.to_string().as_str());
+            }
+        }
+
+        if elapsed >= self.slow_query_threshold {
+            span.in_scope(|| {
+                tracing::warn!(
+                    db.statement = statement.as_str(),
+                    duration_ms = elapsed.as_millis() as u64,
+                    "Slow query"
+                )
+            });
+        }
+
+        result
+    }
+}
+
+/// Collapses whitespace and replaces inline literals with `?`, so that
+/// statements never leak values into traces or logs.
+fn sanitize_statement(statement: &str) -> String {
+    let mut sanitized = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+    let mut previous = ' ';
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // Skips to the closing quote; backslash escapes and doubled
+                // quotes are part of the literal.
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        next if next == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                        }
+                        next if next == c => break,
+                        _ => {}
+                    }
+                }
+                sanitized.push('?');
+                previous = '?';
+            }
+            c if c.is_ascii_digit() && !(previous.is_alphanumeric() || previous == '_') => {
+                while chars
+                    .peek()
+                    .is_some_and(|next| next.is_ascii_digit() || *next == '.')
+                {
+                    chars.next();
+                }
+                sanitized.push('?');
+                previous = '?';
+            }
+            c if c.is_whitespace() => {
+                if previous != ' ' {
+                    sanitized.push(' ');
+                }
+                previous = ' ';
+            }
+            c => {
+                sanitized.push(c);
+                previous = c;
+            }
+        }
+    }
+
+    sanitized.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_statement;
+
+    #[test]
+    fn collapses_whitespace() {
+        assert_eq!(
+            sanitize_statement("\n    SELECT id\n\t FROM posts\n    WHERE id = ?\n  "),
+            "SELECT id FROM posts WHERE id = ?"
+        );
+    }
+
+    #[test]
+    fn replaces_string_literals() {
+        assert_eq!(
+            sanitize_statement("SELECT * FROM users WHERE name = 'alice' AND role = \"admin\""),
+            "SELECT * FROM users WHERE name = ? AND role = ?"
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_quotes_inside_literals() {
+        assert_eq!(
+            sanitize_statement(r"SELECT 'it\'s' , 'it''s', 'a\\' FROM t"),
+            "SELECT ? , ?, ? FROM t"
+        );
+    }
+
+    #[test]
+    fn replaces_unterminated_literals() {
+        assert_eq!(sanitize_statement("WHERE name = 'secret"), "WHERE name = ?");
+    }
+
+    #[test]
+    fn replaces_numbers() {
+        assert_eq!(
+            sanitize_statement("LIMIT 10 OFFSET 20 WHERE price > 9.99"),
+            "LIMIT ? OFFSET ? WHERE price > ?"
+        );
+    }
+
+    #[test]
+    fn keeps_digits_in_identifiers() {
+        assert_eq!(
+            sanitize_statement("SELECT col_1, t2.x FROM table3"),
+            "SELECT col_1, t2.x FROM table3"
+        );
+    }
+}
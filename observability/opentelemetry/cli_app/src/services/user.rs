@@ -1,31 +1,87 @@
-use crate::model::{User, UserStatus};
+use crate::model::{User, UserRole, UserStatus};
+use crate::services::audit::{self, AuditContext, NewAuditEvent};
+use crate::services::query::QueryObserver;
+use crate::settings::Database;
 use chrono::{DateTime, Utc};
-use sqlx::MySqlPool;
+use sqlx::{Execute, Executor, MySql, MySqlPool, Transaction};
 use std::collections::HashMap;
+use std::fmt;
 use tokio::sync::Mutex;
+use tracing::instrument;
+
+/// Password hashes never end up in the audit trail.
+const REDACTED_FIELDS: &[&str] = &["password"];
 
 #[allow(async_fn_in_trait)]
 pub trait UserService {
     async fn get_user_by_id(&self, id: i64) -> anyhow::Result<User>;
     async fn get_user_by_name(&self, name: &str) -> anyhow::Result<User>;
-    async fn create_user(&mut self, req: CreateUserRequest) -> anyhow::Result<User>;
-    async fn update_user(&mut self, id: i64, req: UpdateUserRequest) -> anyhow::Result<User>;
-    async fn delete_user(&mut self, id: i64) -> anyhow::Result<()>;
+    async fn create_user(
+        &self,
+        req: CreateUserRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<User>;
+    async fn update_user(
+        &self,
+        id: i64,
+        req: UpdateUserRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<User>;
+    async fn patch_user(
+        &self,
+        id: i64,
+        req: PatchUserRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<User>;
+    async fn delete_user(&self, id: i64, audit: &AuditContext) -> anyhow::Result<()>;
 }
 
 pub struct CreateUserRequest {
     pub username: String,
     pub password: String,
     pub status: UserStatus,
+    pub role: UserRole,
 }
 
 pub struct UpdateUserRequest {
     pub username: String,
     pub password: String,
     pub status: UserStatus,
+    pub role: UserRole,
     pub last_login: Option<DateTime<Utc>>,
 }
 
+/// A partial update; fields that are `None` keep their current value.
+#[derive(Default)]
+pub struct PatchUserRequest {
+    pub username: Option<String>,
+    /// The hash of the new password.
+    pub password: Option<String>,
+    pub status: Option<UserStatus>,
+    pub role: Option<UserRole>,
+}
+
+impl PatchUserRequest {
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none()
+            && self.password.is_none()
+            && self.status.is_none()
+            && self.role.is_none()
+    }
+}
+
+/// The username already belongs to another user.
+#[derive(Debug)]
+pub struct UsernameTaken(pub String);
+
+impl fmt::Display for UsernameTaken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Username is already taken: {}", self.0)
+    }
+}
+
+impl std::error::Error for UsernameTaken {}
+
 pub struct InMemoryUserStore {
     pub counter: i64,
     pub items: HashMap<i64, User>,
@@ -65,7 +121,11 @@ impl UserService for InMemoryUserService {
         anyhow::bail!("User not found: {}", name)
     }
 
-    async fn create_user(&mut self, req: CreateUserRequest) -> anyhow::Result<User> {
+    async fn create_user(
+        &self,
+        req: CreateUserRequest,
+        _audit: &AuditContext,
+    ) -> anyhow::Result<User> {
         let mut data = self.data.lock().await;
         data.counter += 1;
         let ts = chrono::offset::Utc::now();
@@ -74,6 +134,7 @@ impl UserService for InMemoryUserService {
             username: req.username,
             password: req.password,
             status: req.status,
+            role: req.role,
             created: ts,
             updated: ts,
             last_login: None,
@@ -89,7 +150,12 @@ impl UserService for InMemoryUserService {
         }
     }
 
-    async fn update_user(&mut self, id: i64, req: UpdateUserRequest) -> anyhow::Result<User> {
+    async fn update_user(
+        &self,
+        id: i64,
+        req: UpdateUserRequest,
+        _audit: &AuditContext,
+    ) -> anyhow::Result<User> {
         let mut data = self.data.lock().await;
         let user = data
             .items
@@ -99,6 +165,7 @@ impl UserService for InMemoryUserService {
         user.username = req.username;
         user.password = req.password;
         user.status = req.status;
+        user.role = req.role;
         user.last_login = req.last_login;
 
         match data.items.get(&data.counter) {
@@ -109,7 +176,46 @@ impl UserService for InMemoryUserService {
         }
     }
 
-    async fn delete_user(&mut self, id: i64) -> anyhow::Result<()> {
+    async fn patch_user(
+        &self,
+        id: i64,
+        req: PatchUserRequest,
+        _audit: &AuditContext,
+    ) -> anyhow::Result<User> {
+        let mut data = self.data.lock().await;
+        if let Some(username) = &req.username {
+            if data
+                .items
+                .values()
+                .any(|user| user.id != id && &user.username == username)
+            {
+                return Err(UsernameTaken(username.clone()).into());
+            }
+        }
+
+        let user = data
+            .items
+            .get_mut(&id)
+            .ok_or(anyhow::anyhow!("User not found: {}", id))?;
+
+        if let Some(username) = req.username {
+            user.username = username;
+        }
+        if let Some(password) = req.password {
+            user.password = password;
+        }
+        if let Some(status) = req.status {
+            user.status = status;
+        }
+        if let Some(role) = req.role {
+            user.role = role;
+        }
+        user.updated = chrono::offset::Utc::now();
+
+        Ok(user.clone())
+    }
+
+    async fn delete_user(&self, id: i64, _audit: &AuditContext) -> anyhow::Result<()> {
         let mut data = self.data.lock().await;
         match data.items.remove(&id) {
             None => {
@@ -122,109 +228,233 @@ impl UserService for InMemoryUserService {
 
 pub struct MySQLUserService {
     pub pool: MySqlPool,
+    observer: QueryObserver,
 }
 
 impl MySQLUserService {
-    pub fn new(pool: MySqlPool) -> Self {
-        Self { pool }
+    pub fn new(pool: MySqlPool, settings: &Database) -> Self {
+        Self {
+            pool,
+            observer: QueryObserver::new(settings),
+        }
     }
 }
 
 impl UserService for MySQLUserService {
+    #[instrument(skip(self))]
     async fn get_user_by_id(&self, id: i64) -> anyhow::Result<User> {
-        let res = sqlx::query!(
-            r#"
-            SELECT id, username, password, status, created, updated, last_login
-            FROM users
-            WHERE id = ?
-            "#,
-            id
-        );
-
-        res.fetch_one(&self.pool)
-            .await
-            .map(|row| User {
-                id: row.id as i64,
-                username: row.username,
-                password: row.password,
-                status: UserStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-                last_login: row.last_login,
-            })
-            .map_err(|e| anyhow::anyhow!(e).context(format!("Failed to get user by id: {}", id)))
+        self.select_user(&self.pool, id).await
     }
 
+    #[instrument(skip(self))]
     async fn get_user_by_name(&self, name: &str) -> anyhow::Result<User> {
         let res = sqlx::query!(
             r#"
-            SELECT id, username, password, status, created, updated, last_login
+            SELECT id, username, password, status, role, created, updated, last_login
             FROM users
             WHERE username = ?
             "#,
             name
         );
 
-        res.fetch_one(&self.pool)
+        self.observer
+            .observe(
+                "SELECT",
+                "users",
+                res.sql(),
+                |_| 1,
+                res.fetch_one(&self.pool),
+            )
             .await
-            .map(|row| User {
-                id: row.id as i64,
-                username: row.username,
-                password: row.password,
-                status: UserStatus::from(row.status),
-                created: row.created.unwrap_or_default(),
-                updated: row.updated.unwrap_or_default(),
-                last_login: row.last_login,
+            .and_then(|row| {
+                Ok(User {
+                    id: row.id as i64,
+                    username: row.username,
+                    password: row.password,
+                    status: UserStatus::from(row.status),
+                    role: decode_role(row.role)?,
+                    created: row.created.unwrap_or_default(),
+                    updated: row.updated.unwrap_or_default(),
+                    last_login: row.last_login,
+                })
             })
             .map_err(|e| {
                 anyhow::anyhow!(e).context(format!("Failed to get user by name: {}", name))
             })
     }
 
-    async fn create_user(&mut self, req: CreateUserRequest) -> anyhow::Result<User> {
+    #[instrument(skip(self, req, audit))]
+    async fn create_user(
+        &self,
+        req: CreateUserRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<User> {
+        let mut tx = self.pool.begin().await?;
+
         let query = sqlx::query!(
             r#"
-                INSERT INTO users ( username, password, status, created, updated, last_login )
-                VALUES ( ?, ?, ?, NOW(), NOW(), NULL )
+                INSERT INTO users ( username, password, status, role, created, updated, last_login )
+                VALUES ( ?, ?, ?, ?, NOW(), NOW(), NULL )
             "#,
             req.username,
             req.password,
-            i32::from(req.status)
+            i32::from(req.status),
+            i32::from(req.role)
         );
 
-        let res = query.execute(&self.pool).await?.last_insert_id();
+        let res = self
+            .observer
+            .observe(
+                "INSERT",
+                "users",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?
+            .last_insert_id();
 
         let id: i64 = res
             .try_into()
             .or_else(|_| anyhow::bail!("Failed to convert user id"))?;
 
-        let user = self.get_user_by_id(id).await?;
+        let user = self.select_user(&mut *tx, id).await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "user.create",
+                target_type: "user",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(None, Some(&user), REDACTED_FIELDS),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
 
         Ok(user)
     }
 
-    async fn update_user(&mut self, id: i64, req: UpdateUserRequest) -> anyhow::Result<User> {
+    #[instrument(skip(self, req, audit))]
+    async fn update_user(
+        &self,
+        id: i64,
+        req: UpdateUserRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<User> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_user(&mut tx, id).await?;
+
         let query = sqlx::query!(
             r#"
                 UPDATE users
-                SET username = ?, password = ?, status = ?, updated = NOW(), last_login = ?
+                SET username = ?, password = ?, status = ?, role = ?, updated = NOW(), last_login = ?
                 WHERE id = ?
             "#,
             req.username,
             req.password,
             i32::from(req.status),
+            i32::from(req.role),
             req.last_login,
             id
         );
 
-        query.execute(&self.pool).await?;
+        self.observer
+            .observe(
+                "UPDATE",
+                "users",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?;
+
+        let user = self.select_user(&mut *tx, id).await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "user.update",
+                target_type: "user",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&user), REDACTED_FIELDS),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    #[instrument(skip(self, req, audit))]
+    async fn patch_user(
+        &self,
+        id: i64,
+        req: PatchUserRequest,
+        audit: &AuditContext,
+    ) -> anyhow::Result<User> {
+        let mut tx = self.pool.begin().await?;
 
-        let user = self.get_user_by_id(id).await?;
+        let before = self.lock_user(&mut tx, id).await?;
+
+        let query = sqlx::query!(
+            r#"
+                UPDATE users
+                SET username = COALESCE(?, username), password = COALESCE(?, password),
+                    status = COALESCE(?, status), role = COALESCE(?, role), updated = NOW()
+                WHERE id = ?
+            "#,
+            req.username,
+            req.password,
+            req.status.map(i32::from),
+            req.role.map(i32::from),
+            id
+        );
+
+        self.observer
+            .observe(
+                "UPDATE",
+                "users",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await
+            .map_err(|e| username_error(e, req.username.as_deref().unwrap_or_default()))?;
+
+        let user = self.select_user(&mut *tx, id).await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "user.update",
+                target_type: "user",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), Some(&user), REDACTED_FIELDS),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
 
         Ok(user)
     }
 
-    async fn delete_user(&mut self, id: i64) -> anyhow::Result<()> {
+    #[instrument(skip(self, audit))]
+    async fn delete_user(&self, id: i64, audit: &AuditContext) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = self.lock_user(&mut tx, id).await?;
+
         let query = sqlx::query!(
             r#"
                 DELETE FROM users
@@ -233,8 +463,115 @@ impl UserService for MySQLUserService {
             id
         );
 
-        query.execute(&self.pool).await?;
+        self.observer
+            .observe(
+                "DELETE",
+                "users",
+                query.sql(),
+                |r| r.rows_affected(),
+                query.execute(&mut *tx),
+            )
+            .await?;
+
+        audit::record_event(
+            &mut tx,
+            &self.observer,
+            audit,
+            NewAuditEvent {
+                action: "user.delete",
+                target_type: "user",
+                target_id: Some(id.to_string()),
+                changes: audit::diff(Some(&before), None, REDACTED_FIELDS),
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
 
         Ok(())
     }
 }
+
+impl MySQLUserService {
+    async fn select_user<'e, E>(&self, executor: E, id: i64) -> anyhow::Result<User>
+    where
+        E: Executor<'e, Database = MySql>,
+    {
+        let res = sqlx::query!(
+            r#"
+            SELECT id, username, password, status, role, created, updated, last_login
+            FROM users
+            WHERE id = ?
+            "#,
+            id
+        );
+
+        self.observer
+            .observe("SELECT", "users", res.sql(), |_| 1, res.fetch_one(executor))
+            .await
+            .and_then(|row| {
+                Ok(User {
+                    id: row.id as i64,
+                    username: row.username,
+                    password: row.password,
+                    status: UserStatus::from(row.status),
+                    role: decode_role(row.role)?,
+                    created: row.created.unwrap_or_default(),
+                    updated: row.updated.unwrap_or_default(),
+                    last_login: row.last_login,
+                })
+            })
+            .map_err(|e| anyhow::anyhow!(e).context(format!("Failed to get user by id: {}", id)))
+    }
+
+    async fn lock_user(&self, tx: &mut Transaction<'_, MySql>, id: i64) -> anyhow::Result<User> {
+        let res = sqlx::query!(
+            r#"
+            SELECT id, username, password, status, role, created, updated, last_login
+            FROM users
+            WHERE id = ?
+            FOR UPDATE
+            "#,
+            id
+        );
+
+        self.observer
+            .observe(
+                "SELECT",
+                "users",
+                res.sql(),
+                |row| row.is_some() as u64,
+                res.fetch_optional(&mut **tx),
+            )
+            .await?
+            .map(|row| {
+                Ok::<_, sqlx::Error>(User {
+                    id: row.id as i64,
+                    username: row.username,
+                    password: row.password,
+                    status: UserStatus::from(row.status),
+                    role: decode_role(row.role)?,
+                    created: row.created.unwrap_or_default(),
+                    updated: row.updated.unwrap_or_default(),
+                    last_login: row.last_login,
+                })
+            })
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("User not found: {}", id))
+    }
+}
+
+/// Fails on role values that no `UserRole` stands for, instead of granting
+/// them the rights of an author.
+fn decode_role(value: i32) -> Result<UserRole, sqlx::Error> {
+    UserRole::try_from(value).map_err(|e| sqlx::Error::Decode(e.into()))
+}
+
+fn username_error(e: sqlx::Error, username: &str) -> anyhow::Error {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            UsernameTaken(username.to_string()).into()
+        }
+        _ => e.into(),
+    }
+}
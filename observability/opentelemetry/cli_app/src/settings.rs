@@ -1,26 +1,288 @@
+use crate::model::UserRole;
 use config::{Config, Environment, File};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Default, Clone)]
 #[allow(unused)]
 pub struct Database {
     pub url: Option<String>,
+    pub slow_query_threshold_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerKind {
+    #[default]
+    Always,
+    Never,
+    Ratio,
+    ParentRatio,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum OtlpProtocol {
+    #[serde(rename = "grpc")]
+    Grpc,
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+    #[default]
+    #[serde(rename = "http/json")]
+    HttpJson,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpCompression {
+    Gzip,
+    Zstd,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct SpanLimits {
+    pub max_events_per_span: Option<u32>,
+    pub max_attributes_per_span: Option<u32>,
+    pub max_links_per_span: Option<u32>,
+    pub max_attributes_per_event: Option<u32>,
+    pub max_attributes_per_link: Option<u32>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
 pub struct OtlpTarget {
     pub address: String,
     pub authorization: Option<String>,
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+    pub timeout_seconds: Option<u64>,
+    pub compression: Option<OtlpCompression>,
+    pub tls_ca_path: Option<String>,
+    pub service_name: Option<String>,
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub sampler: SamplerKind,
+    pub sampler_ratio: Option<f64>,
+    #[serde(default)]
+    pub span_limits: SpanLimits,
+    #[serde(default)]
+    pub resource_attributes: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Full,
+    Pretty,
+    Compact,
+    Json,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    #[default]
+    Never,
+    Daily,
+    Size,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct LogFile {
+    pub path: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    pub max_size_mb: Option<u64>,
+    pub max_files: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]
 #[allow(unused)]
 pub struct Logging {
-    pub log_level: Option<String>,
+    #[serde(alias = "log_level")]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub format: LogFormat,
+    pub file: Option<LogFile>,
+    #[serde(default)]
+    pub allow_debug_header: bool,
     pub otlp_target: Option<OtlpTarget>,
 }
 
+/// Maps a verified client certificate to a principal. The certificate
+/// matches when its subject equals `subject` or one of its SANs equals `san`.
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct ClientPrincipal {
+    pub subject: Option<String>,
+    pub san: Option<String>,
+    pub name: String,
+    pub role: UserRole,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct Tls {
+    pub cert_path: String,
+    pub key_path: String,
+    pub reload_interval_seconds: Option<u64>,
+    pub redirect_http_port: Option<u16>,
+    #[serde(default)]
+    pub client_ca_paths: Vec<String>,
+    #[serde(default)]
+    pub client_principals: Vec<ClientPrincipal>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    Gzip,
+    Br,
+    Zstd,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct Compression {
+    #[serde(default = "Compression::default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "Compression::default_algorithms")]
+    pub algorithms: Vec<ContentEncoding>,
+    pub min_size_bytes: Option<u16>,
+    /// Prefixes of the content types that are compressed, e.g. `text/`.
+    #[serde(default = "Compression::default_content_types")]
+    pub content_types: Vec<String>,
+}
+
+impl Compression {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_algorithms() -> Vec<ContentEncoding> {
+        vec![
+            ContentEncoding::Gzip,
+            ContentEncoding::Br,
+            ContentEncoding::Zstd,
+        ]
+    }
+
+    fn default_content_types() -> Vec<String> {
+        vec!["application/json".to_string(), "text/".to_string()]
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            algorithms: Self::default_algorithms(),
+            min_size_bytes: None,
+            content_types: Self::default_content_types(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[allow(unused)]
+pub struct Server {
+    /// IP addresses, optionally with a port, or `unix:<path>` entries. Also
+    /// accepts a comma separated string, e.g. from the environment.
+    #[serde(default, deserialize_with = "string_or_list")]
+    pub bind: Vec<String>,
+    pub port: Option<u16>,
+    /// Octal permissions of Unix socket files, e.g. `660`.
+    pub unix_socket_mode: Option<String>,
+    pub tls: Option<Tls>,
+    pub request_timeout_seconds: Option<u64>,
+    pub header_read_timeout_seconds: Option<u64>,
+    pub idle_timeout_seconds: Option<u64>,
+    pub keep_alive: Option<bool>,
+    /// Open connections of all listeners together. Further connections are
+    /// not accepted until one is closed.
+    pub max_connections: Option<usize>,
+    pub body_limit_bytes: Option<usize>,
+    /// Body limits for single routes, keyed by route path, e.g. `/v1/posts`.
+    #[serde(default)]
+    pub route_body_limits: HashMap<String, usize>,
+    /// HTTP/2 via ALPN with TLS and with prior knowledge (h2c) without.
+    pub http2: Option<bool>,
+    /// Also serves HTTP/3 over QUIC on the UDP ports of all TCP addresses.
+    /// Requires `tls` and the `http3` cargo feature.
+    #[serde(default)]
+    pub http3: bool,
+    #[serde(default)]
+    pub compression: Compression,
+}
+
+fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .collect(),
+        StringOrList::List(values) => values,
+    })
+}
+
+/// `Cache-Control` values for read routes. `routes` overrides `public` for
+/// single routes, keyed by route path, e.g. `/v1/posts`.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[allow(unused)]
+pub struct HttpCache {
+    pub public: Option<String>,
+    pub private: Option<String>,
+    #[serde(default)]
+    pub routes: HashMap<String, String>,
+}
+
+impl HttpCache {
+    pub fn public_for(&self, route: &str) -> String {
+        self.routes
+            .get(route)
+            .or(self.public.as_ref())
+            .cloned()
+            .unwrap_or("public, max-age=60".to_string())
+    }
+
+    pub fn private(&self) -> String {
+        self.private
+            .clone()
+            .unwrap_or("private, no-store".to_string())
+    }
+}
+
+/// The background task publishing and unpublishing scheduled posts, which
+/// runs every `interval_seconds` (30 by default) unless disabled.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[allow(unused)]
+pub struct Scheduler {
+    pub enabled: Option<bool>,
+    pub interval_seconds: Option<u64>,
+}
+
+/// How many post revisions are kept. Revisions beyond the newest `keep` of a
+/// post, or older than `max_age_days`, are removed when a post is updated;
+/// all are kept by default.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[allow(unused)]
+pub struct Revisions {
+    pub keep: Option<u32>,
+    pub max_age_days: Option<u32>,
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 #[allow(unused)]
 pub struct ConfigInfo {
@@ -36,6 +298,14 @@ pub struct Settings {
     #[serde(default)]
     pub logging: Logging,
     #[serde(default)]
+    pub server: Server,
+    #[serde(default)]
+    pub http_cache: HttpCache,
+    #[serde(default)]
+    pub scheduler: Scheduler,
+    #[serde(default)]
+    pub revisions: Revisions,
+    #[serde(default)]
     pub config: ConfigInfo,
     pub token_secret: Option<String>,
     pub token_timeout_seconds: Option<i64>,
@@ -63,3 +333,56 @@ impl Settings {
         Ok(settings)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{SamplerKind, Settings};
+
+    #[test]
+    fn loads_otlp_target_with_snake_case_keys() {
+        let path =
+            std::env::temp_dir().join(format!("cli_app_settings_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+                [logging.otlp_target]
+                address = "http://localhost:4318"
+                timeout_seconds = 5
+                tls_ca_path = "/etc/otel/ca.pem"
+                service_name = "blog"
+                sampler = "ratio"
+                sampler_ratio = 0.25
+
+                [logging.otlp_target.span_limits]
+                max_events_per_span = 64
+                max_attributes_per_span = 32
+                max_links_per_span = 16
+                max_attributes_per_event = 8
+                max_attributes_per_link = 4
+
+                [logging.otlp_target.resource_attributes]
+                team = "blog"
+            "#,
+        )
+        .unwrap();
+
+        let settings = Settings::new(path.to_str(), "CLI_APP_SETTINGS_TEST");
+        std::fs::remove_file(&path).unwrap();
+        let target = settings.unwrap().logging.otlp_target.unwrap();
+
+        assert_eq!(target.timeout_seconds, Some(5));
+        assert_eq!(target.tls_ca_path.as_deref(), Some("/etc/otel/ca.pem"));
+        assert_eq!(target.service_name.as_deref(), Some("blog"));
+        assert_eq!(target.sampler, SamplerKind::Ratio);
+        assert_eq!(target.sampler_ratio, Some(0.25));
+        assert_eq!(target.span_limits.max_events_per_span, Some(64));
+        assert_eq!(target.span_limits.max_attributes_per_span, Some(32));
+        assert_eq!(target.span_limits.max_links_per_span, Some(16));
+        assert_eq!(target.span_limits.max_attributes_per_event, Some(8));
+        assert_eq!(target.span_limits.max_attributes_per_link, Some(4));
+        assert_eq!(
+            target.resource_attributes.get("team").map(String::as_str),
+            Some("blog")
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::services::post::InMemoryPostService;
+use crate::logging::{self, LogLevelHandle};
+use crate::services::audit::MySQLAuditService;
+use crate::services::post::MySQLPostService;
 use crate::services::user::MySQLUserService;
 use crate::settings::Settings;
 use arc_swap::ArcSwap;
@@ -8,15 +10,45 @@ use std::sync::Arc;
 pub struct ApplicationState {
     pub settings: ArcSwap<Settings>,
     pub user_service: Arc<MySQLUserService>,
-    pub post_service: Arc<InMemoryPostService>,
+    pub post_service: Arc<MySQLPostService>,
+    pub audit_service: Arc<MySQLAuditService>,
+    pub log_level: LogLevelHandle,
 }
 
 impl ApplicationState {
-    pub fn new(settings: &Settings, pool: MySqlPool) -> anyhow::Result<Self> {
+    pub fn new(
+        settings: &Settings,
+        pool: MySqlPool,
+        log_level: LogLevelHandle,
+    ) -> anyhow::Result<Self> {
         Ok(Self {
             settings: ArcSwap::new(Arc::new((*settings).clone())),
-            user_service: Arc::new(MySQLUserService::new(pool)),
-            post_service: Arc::new(InMemoryPostService::default()),
+            user_service: Arc::new(MySQLUserService::new(pool.clone(), &settings.database)),
+            post_service: Arc::new(MySQLPostService::new(
+                pool.clone(),
+                &settings.database,
+                &settings.revisions,
+            )),
+            audit_service: Arc::new(MySQLAuditService::new(pool, &settings.database)),
+            log_level,
         })
     }
+
+    /// Reads the configuration again from the original file and environment
+    /// and applies the parts that can change without a restart.
+    pub fn reload_settings(&self) -> anyhow::Result<()> {
+        let current = self.settings.load();
+        let settings = Settings::new(
+            current.config.location.as_deref(),
+            current.config.env_prefix.as_deref().unwrap_or("APP"),
+        )?;
+
+        self.log_level
+            .set_default(&logging::default_level(&settings.logging))?;
+        self.settings.store(Arc::new(settings));
+
+        tracing::info!("Settings reloaded");
+
+        Ok(())
+    }
 }
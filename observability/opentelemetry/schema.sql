@@ -3,6 +3,7 @@ CREATE TABLE users (
   username VARCHAR(255) NOT NULL,
   password VARCHAR(255) NOT NULL,
   status INT NOT NULL DEFAULT 1,
+  role INT NOT NULL DEFAULT 1,
   created TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
   updated TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
   last_login TIMESTAMP,
@@ -16,7 +17,64 @@ CREATE TABLE posts (
   title VARCHAR(255) NOT NULL,
   content TEXT NOT NULL,
   status integer NOT NULL DEFAULT 1,
+  review_comment TEXT,
+  publish_at TIMESTAMP NULL,
+  unpublish_at TIMESTAMP NULL,
+  visibility INT NOT NULL DEFAULT 1,
+  password_hash VARCHAR(255),
+  version INT NOT NULL DEFAULT 1,
   created TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
   updated TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
-  UNIQUE (slug)
+  deleted_at TIMESTAMP NULL,
+  UNIQUE (slug),
+  INDEX (status, publish_at),
+  INDEX (status, unpublish_at),
+  INDEX (deleted_at)
 );
+
+CREATE TABLE post_slug_history (
+  slug VARCHAR(255) NOT NULL PRIMARY KEY,
+  post_id INT NOT NULL,
+  created TIMESTAMP NOT NULL,
+  INDEX (post_id),
+  FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE
+);
+
+CREATE TABLE post_revisions (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY,
+  post_id INT NOT NULL,
+  revision INT NOT NULL,
+  author VARCHAR(255),
+  message VARCHAR(1024),
+  title VARCHAR(255) NOT NULL,
+  content TEXT NOT NULL,
+  created TIMESTAMP NOT NULL,
+  UNIQUE (post_id, revision),
+  FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE
+);
+
+CREATE TABLE audit_events (
+  id BIGINT AUTO_INCREMENT PRIMARY KEY,
+  actor VARCHAR(255),
+  action VARCHAR(64) NOT NULL,
+  target_type VARCHAR(64) NOT NULL,
+  target_id VARCHAR(255),
+  changes TEXT NOT NULL,
+  ip VARCHAR(64),
+  request_id VARCHAR(128),
+  created TIMESTAMP NOT NULL,
+  prev_hash CHAR(64) NOT NULL,
+  hash CHAR(64) NOT NULL,
+  INDEX (actor),
+  INDEX (target_type, target_id),
+  INDEX (created)
+);
+
+-- Hash of the last audit event. Writers lock this single row to append to
+-- the chain one at a time.
+CREATE TABLE audit_chain_head (
+  id TINYINT PRIMARY KEY,
+  hash CHAR(64) NOT NULL
+);
+
+INSERT INTO audit_chain_head (id, hash) VALUES (1, '');
@@ -1,6 +1,7 @@
 mod hello;
 mod serve;
 
+use crate::logging;
 use crate::settings::Settings;
 use clap::{ArgMatches, Command};
 
@@ -12,6 +13,8 @@ pub fn configure(command: Command) -> Command {
 }
 
 pub fn handle(matches: &ArgMatches, settings: &Settings) -> anyhow::Result<()> {
+    let _logging_guard = logging::init(&settings.logging)?;
+
     if let Some((cmd, matches)) = matches.subcommand() {
         match cmd {
             hello::COMMAND_NAME => hello::handle(matches, settings)?,
@@ -4,11 +4,6 @@ use clap::{value_parser, Arg, ArgMatches, Command};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use tower_http::trace::TraceLayer;
-use tracing::level_filters::LevelFilter;
-use tracing::Level;
-use tracing_subscriber::fmt;
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
 
 pub const COMMAND_NAME: &str = "serve";
 
@@ -37,12 +32,6 @@ fn start_tokio(port: u16, settings: &Settings) -> anyhow::Result<()> {
         .enable_all()
         .build()?
         .block_on(async move {
-            let subscriber = tracing_subscriber::registry()
-                .with(LevelFilter::from_level(Level::TRACE))
-                .with(fmt::Layer::default());
-
-            subscriber.init();
-
             let db_url = settings
                 .database
                 .url
@@ -1,5 +1,6 @@
 pub mod api;
 pub mod commands;
+pub mod logging;
 pub mod model;
 pub mod services;
 pub mod settings;
@@ -0,0 +1,82 @@
+use crate::settings::{LogFile, LogFormat, LogRotation, Logging};
+use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Keeps the log file writer alive. Dropping it flushes buffered log lines.
+pub struct LoggingGuard {
+    _file_guard: Option<WorkerGuard>,
+}
+
+/// Sets up the subscriber shared by all subcommands. JSON lines carry no
+/// trace IDs here, as this application does not export traces.
+pub fn init(logging: &Logging) -> anyhow::Result<LoggingGuard> {
+    let mut outputs = vec![output_layer(logging.format, std::io::stdout, true)];
+
+    let file_guard = match &logging.file {
+        Some(file) => {
+            let (writer, guard) = tracing_appender::non_blocking(file_appender(file)?);
+            outputs.push(output_layer(logging.format, writer, false));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(outputs.with_filter(EnvFilter::try_new(level(logging))?))
+        .try_init()?;
+
+    Ok(LoggingGuard {
+        _file_guard: file_guard,
+    })
+}
+
+/// `RUST_LOG` takes precedence over `logging.level`, which accepts the full
+/// `EnvFilter` directive syntax, e.g. `info,cli_app=debug,sqlx=warn`.
+fn level(logging: &Logging) -> String {
+    std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|level| !level.is_empty())
+        .or(logging.level.clone())
+        .unwrap_or("info".to_string())
+}
+
+fn output_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+
+    match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+fn file_appender(file: &LogFile) -> anyhow::Result<BasicRollingFileAppender> {
+    let mut condition = RollingConditionBasic::new();
+
+    match file.rotation {
+        LogRotation::Never => {}
+        LogRotation::Daily => condition = condition.daily(),
+        LogRotation::Size => {
+            condition = condition.max_size(file.max_size_mb.unwrap_or(100) * 1024 * 1024)
+        }
+    }
+
+    Ok(BasicRollingFileAppender::new(
+        &file.path,
+        condition,
+        file.max_files.unwrap_or(7),
+    )?)
+}
@@ -7,10 +7,43 @@ pub struct Database {
     pub url: Option<String>,
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Full,
+    Pretty,
+    Compact,
+    Json,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    #[default]
+    Never,
+    Daily,
+    Size,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct LogFile {
+    pub path: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    pub max_size_mb: Option<u64>,
+    pub max_files: Option<usize>,
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 #[allow(unused)]
 pub struct Logging {
-    pub log_level: Option<String>,
+    #[serde(alias = "log_level")]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub format: LogFormat,
+    pub file: Option<LogFile>,
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]